@@ -0,0 +1,30 @@
+use re2dfa_derive::Lexer;
+
+#[derive(Lexer, Debug)]
+enum Token {
+  #[re(r"\d+(\.\d+)?")]
+  Num,
+  #[re(r"[a-zA-Z_]\w*")]
+  Ident,
+  #[re(r"\+")] #[re("-")] #[re(r"\*")] #[re("/")]
+  Op,
+  #[re(r"\(")]
+  LParen,
+  #[re(r"\)")]
+  RParen,
+  #[skip]
+  #[re(r"\s+")]
+  Space,
+  #[allow(dead_code)]
+  Eof,
+}
+
+fn main() {
+  let input = std::env::args().nth(1).unwrap_or_else(|| "x * (3.14 + 2) ? y".to_owned());
+  for t in Token::lex(input.as_bytes()) {
+    match t {
+      Ok((t, range)) => println!("{:?} {:?}", t, &input[range]),
+      Err(pos) => println!("error at {}", pos),
+    }
+  }
+}
@@ -0,0 +1,94 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, format_ident};
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, LitStr};
+use re2dfa::TokenSet;
+
+// each unit variant of the enum can have several `#[re("...")]` attributes, and if it has any, a `#[skip]` attribute
+// the generated code doesn't depend on re2dfa, it implements 2 methods on the enum:
+// `next_token(input: &[u8]) -> Option<(Self, usize)>`: the longest match at the beginning of `input`, skipped variants included
+// `lex(input: &[u8]) -> impl Iterator<Item=Result<(Self, Range<usize>), usize>>`: has the same effect as `re2dfa::Lexer`,
+// except that skipped variants are not yielded
+// if multiple variants accept the longest match, the variant declared first is chosen
+#[proc_macro_derive(Lexer, attributes(re, skip))]
+pub fn lexer(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  match lexer_impl(&input) {
+    Ok(ts) => ts.into(),
+    Err(e) => e.to_compile_error().into(),
+  }
+}
+
+fn lexer_impl(input: &DeriveInput) -> Result<TokenStream2, Error> {
+  let data = match &input.data {
+    Data::Enum(data) => data,
+    _ => return Err(Error::new_spanned(input, "#[derive(Lexer)] can only be used on enum")),
+  };
+  // each variant with at least one `#[re]` is a token, `variants` is indexed by token id
+  let (mut ts, mut variants) = (TokenSet::new(), Vec::new());
+  for v in &data.variants {
+    if !matches!(v.fields, Fields::Unit) {
+      return Err(Error::new_spanned(v, "#[derive(Lexer)] requires all variants to be unit variants"));
+    }
+    let skip = v.attrs.iter().find(|a| a.path().is_ident("skip"));
+    if let Some(skip) = skip {
+      if !v.attrs.iter().any(|a| a.path().is_ident("re")) {
+        return Err(Error::new_spanned(skip, "#[skip] requires at least one #[re(\"...\")] on the variant"));
+      }
+    }
+    for a in v.attrs.iter().filter(|a| a.path().is_ident("re")) {
+      let lit = a.parse_args::<LitStr>()?;
+      match ts.add(&v.ident.to_string(), lit.value().as_bytes()) {
+        Ok(t) => { t.skip(skip.is_some()); }
+        Err(e) => return Err(Error::new_spanned(a, format!("invalid regex: {} at byte {}", e.kind, e.offset))),
+      }
+    }
+    if variants.len() < ts.tokens.len() { variants.push(&v.ident); }
+  }
+  let skips = ts.tokens.iter().map(|t| t.skip);
+  // don't use `gen_rust_with`, the constants named after variants may conflict with other items in the generated module
+  let tables = ts.build().gen_rust().to_string().parse::<TokenStream2>()?;
+  let (ident, mod_ident) = (&input.ident, format_ident!("__{}_lexer", input.ident));
+  let ids = 0..variants.len() as u32;
+  let token_num = variants.len();
+  Ok(quote! {
+    const _: () = {
+      #[allow(non_snake_case)]
+      mod #mod_ident { #tables }
+
+      static SKIP: [bool; #token_num] = [#(#skips),*];
+
+      fn variant(id: u32) -> #ident {
+        match id { #(#ids => #ident::#variants,)* _ => unreachable!() }
+      }
+
+      // unit-only enum can't have generic parameters, because they must be used
+      impl #ident {
+        pub fn next_token(input: &[u8]) -> Option<(Self, usize)> {
+          let (id, len) = #mod_ident::next_token(input)?;
+          Some((variant(id), len))
+        }
+
+        pub fn lex(input: &[u8]) -> impl Iterator<Item=Result<(Self, std::ops::Range<usize>), usize>> + '_ {
+          let mut pos = 0;
+          std::iter::from_fn(move || loop {
+            let start = pos;
+            if start == input.len() { return None; }
+            match #mod_ident::next_token(&input[start..]) {
+              Some((id, len)) => {
+                pos += len;
+                if !SKIP[id as usize] {
+                  return Some(Ok((variant(id), start..pos)));
+                }
+              }
+              None => {
+                pos += 1;
+                return Some(Err(start));
+              }
+            }
+          })
+        }
+      }
+    };
+  })
+}
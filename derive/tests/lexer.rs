@@ -0,0 +1,46 @@
+use re2dfa_derive::Lexer;
+
+#[derive(Lexer, Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+  // declared before `Ident`, so it wins on "if"
+  #[re("if")]
+  If,
+  #[re(r"[a-z]\w*")]
+  Ident,
+  #[re(r"\d+")] #[re(r"0x[0-9a-f]+")]
+  Int,
+  #[re("==")] #[re("=")]
+  Op,
+  #[skip]
+  #[re(r"\s+")] #[re(r"#[^\n]*")]
+  Space,
+  // no `#[re]`, never produced
+  #[allow(dead_code)]
+  Eof,
+}
+
+#[test]
+fn next_token() {
+  use Token::*;
+  assert_eq!(Token::next_token(b"if"), Some((If, 2)));
+  assert_eq!(Token::next_token(b"iff"), Some((Ident, 3)));
+  assert_eq!(Token::next_token(b"0x1fz"), Some((Int, 4)));
+  assert_eq!(Token::next_token(b"==="), Some((Op, 2)));
+  // skipped variants are still returned by `next_token`
+  assert_eq!(Token::next_token(b"  \n x"), Some((Space, 4)));
+  assert_eq!(Token::next_token(b"# c\nx"), Some((Space, 3)));
+  assert_eq!(Token::next_token(b"$if"), None);
+  assert_eq!(Token::next_token(b""), None);
+}
+
+#[test]
+fn lex() {
+  use Token::*;
+  let input = b"if x == 0x1f # c\n$iff=1";
+  let tokens = Token::lex(input).collect::<Vec<_>>();
+  assert_eq!(tokens, [Ok((If, 0..2)), Ok((Ident, 3..4)), Ok((Op, 5..7)), Ok((Int, 8..12)), Err(17), Ok((Ident, 18..21)),
+    Ok((Op, 21..22)), Ok((Int, 22..23))]);
+  assert_eq!(Token::lex(b"").count(), 0);
+  assert_eq!(Token::lex(b" \t# only skipped").count(), 0);
+  assert_eq!(Token::lex(b"$$").collect::<Vec<_>>(), [Err(0), Err(1)]);
+}
@@ -0,0 +1,5 @@
+// the errors reported by `#[derive(Lexer)]` are spanned at the offending attribute or item
+#[test]
+fn ui() {
+  trybuild::TestCases::new().compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,12 @@
+use re2dfa_derive::Lexer;
+
+#[derive(Lexer)]
+enum Token {
+  #[re("a")]
+  A(u32),
+}
+
+#[derive(Lexer)]
+struct S;
+
+fn main() {}
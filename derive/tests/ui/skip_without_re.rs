@@ -0,0 +1,11 @@
+use re2dfa_derive::Lexer;
+
+#[derive(Lexer)]
+enum Token {
+  #[re("a")]
+  A,
+  #[skip]
+  B,
+}
+
+fn main() {}
@@ -0,0 +1,174 @@
+use std::fmt;
+use crate::*;
+
+// write `s` as a string literal, non-printable bytes are escaped
+pub(crate) fn escape<'a>(s: &'a [u8]) -> impl fmt::Display + 'a {
+  fn2display(move |f| {
+    f.write_str("\"")?;
+    for &ch in s { write!(f, "{}", std::ascii::escape_default(ch))?; }
+    f.write_str("\"")
+  })
+}
+
+// a representative byte for each equivalence class, printable ones are preferred, so examples are readable
+pub(crate) fn ec_repr(ec: &[u8; 256], ec_num: usize) -> Vec<u8> {
+  let mut repr = vec![None; ec_num];
+  for pass in 0..2 {
+    for ch in 0..=255u8 {
+      let r = &mut repr[ec[ch as usize] as usize];
+      if r.is_none() && (pass == 1 || ch.is_ascii_graphic()) { *r = Some(ch); }
+    }
+  }
+  repr.into_iter().map(|r| r.unwrap()).collect()
+}
+
+impl Dfa {
+  pub(crate) fn ec_repr(&self) -> Vec<u8> { ec_repr(&self.ec, self.ec_num) }
+
+  // `parent[i] == (j, k)` means the shortest path from start to node i ends with the edge from j via k
+  pub(crate) fn bfs_parent(&self) -> Vec<Option<(u32, u8)>> {
+    let mut parent = vec![None; self.nodes.len()];
+    let mut q = std::collections::VecDeque::new();
+    q.push_back(0);
+    while let Some(cur) = q.pop_front() {
+      let mut edges = self.nodes[cur as usize].1.iter().map(|(&k, &out)| (k, out)).collect::<Vec<_>>();
+      // make the result independent of the iteration order of HashMap
+      edges.sort_unstable();
+      for (k, out) in edges {
+        if out != 0 && parent[out as usize].is_none() {
+          parent[out as usize] = Some((cur, k));
+          q.push_back(out);
+        }
+      }
+    }
+    parent
+  }
+
+  // the shortest string from start to `node`, `repr` comes from `ec_repr`, `parent` comes from `bfs_parent`
+  pub(crate) fn path(repr: &[u8], parent: &[Option<(u32, u8)>], mut node: u32) -> Vec<u8> {
+    let mut ret = Vec::new();
+    while let Some((prev, k)) = parent[node as usize] {
+      ret.push(repr[k as usize]);
+      node = prev;
+    }
+    ret.reverse();
+    ret
+  }
+}
+
+// a pattern that can never be the result of matching, because for each string it accepts,
+// another pattern with higher priority also accepts it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shadowed {
+  pub id: u32,
+  // (the shortest string accepted by pattern `id`, the pattern that wins on it), None if pattern `id` accepts nothing
+  pub example: Option<(Vec<u8>, u32)>,
+}
+
+impl fmt::Display for Shadowed {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { self.fmt_impl(f, &|id| id.to_string()) }
+}
+
+impl Shadowed {
+  // `self` should be returned by `tokens.build_with_warnings()`
+  pub fn display_with<'a>(&'a self, tokens: &'a TokenSet) -> impl fmt::Display + 'a {
+    fn2display(move |f| self.fmt_impl(f, &|id| tokens.name(id).to_owned()))
+  }
+
+  fn fmt_impl(&self, f: &mut fmt::Formatter, name: &dyn Fn(u32) -> String) -> fmt::Result {
+    write!(f, "pattern {} can never be matched", name(self.id))?;
+    match &self.example {
+      Some((s, winner)) => write!(f, ", e.g., {} is matched by pattern {}", escape(s), name(*winner)),
+      None => f.write_str(", because it accepts nothing"),
+    }
+  }
+}
+
+// find the patterns in 0..pattern_num that are not the accept id of any state in `raw`
+// `raw` and `sets` come from `Dfa::from_nfa_with_sets(nfa)`, and `map` maps the accept ids of `nfa` to those of `raw`
+pub(crate) fn shadowed(nfa: &Nfa, raw: &Dfa, sets: &[Box<[u32]>], pattern_num: usize, map: impl Fn(u32) -> u32) -> Vec<Shadowed> {
+  let mut win = vec![false; pattern_num];
+  for &(acc, _) in &raw.nodes {
+    if let Some(acc) = acc { win[acc as usize] = true; }
+  }
+  let mut ret = (0..pattern_num as u32).filter(|&id| !win[id as usize]).map(|id| Shadowed { id, example: None }).collect::<Vec<_>>();
+  if ret.is_empty() { return ret; }
+  let (repr, parent) = (raw.ec_repr(), raw.bfs_parent());
+  let mut missing = ret.len();
+  // states are in bfs order, so the first state found for each pattern gives the shortest example
+  for (node, set) in sets.iter().enumerate() {
+    bitset::ibs(set).ones(|i| if let Some(id) = nfa.nodes[i].id {
+      if let Ok(pos) = ret.binary_search_by_key(&map(id), |s| s.id) {
+        let s = &mut ret[pos];
+        if s.example.is_none() {
+          // `winner` must be Some, because the nfa set contains an accepting state
+          s.example = Some((Dfa::path(&repr, &parent, node as u32), raw.nodes[node].0.unwrap()));
+          missing -= 1;
+        }
+      }
+    });
+    if missing == 0 { break; }
+  }
+  ret
+}
+
+// two patterns that accept a common string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Overlap {
+  // ids.0 < ids.1
+  pub ids: (u32, u32),
+  // the shortest string accepted by both patterns
+  pub example: Vec<u8>,
+  // the pattern that wins on `example`, it may be neither of `ids` if another pattern with higher priority also accepts it
+  pub winner: u32,
+}
+
+impl fmt::Display for Overlap {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { self.fmt_impl(f, &|id| id.to_string()) }
+}
+
+impl Overlap {
+  // `self` should be returned by `tokens.overlaps()`
+  pub fn display_with<'a>(&'a self, tokens: &'a TokenSet) -> impl fmt::Display + 'a {
+    fn2display(move |f| self.fmt_impl(f, &|id| tokens.name(id).to_owned()))
+  }
+
+  fn fmt_impl(&self, f: &mut fmt::Formatter, name: &dyn Fn(u32) -> String) -> fmt::Result {
+    write!(f, "pattern {} and {} both accept {}, which is matched by pattern {}",
+           name(self.ids.0), name(self.ids.1), escape(&self.example), name(self.winner))
+  }
+}
+
+// all pairs of patterns that accept a common string, sorted by `ids`, the arguments are the same as `shadowed`
+pub(crate) fn overlaps(nfa: &Nfa, raw: &Dfa, sets: &[Box<[u32]>], map: impl Fn(u32) -> u32) -> Vec<Overlap> {
+  let (repr, parent) = (raw.ec_repr(), raw.bfs_parent());
+  let mut found = HashMap::default();
+  let mut ids = Vec::new();
+  for (node, set) in sets.iter().enumerate() {
+    ids.clear();
+    bitset::ibs(set).ones(|i| if let Some(id) = nfa.nodes[i].id { ids.push(map(id)); });
+    ids.sort_unstable();
+    ids.dedup();
+    for (i, &id1) in ids.iter().enumerate() {
+      for &id2 in &ids[i + 1..] {
+        // states are in bfs order, so the first state found for each pair gives the shortest example
+        found.entry((id1, id2)).or_insert_with(|| Overlap {
+          ids: (id1, id2),
+          example: Dfa::path(&repr, &parent, node as u32),
+          winner: raw.nodes[node].0.unwrap(),
+        });
+      }
+    }
+  }
+  let mut ret = found.into_values().collect::<Vec<_>>();
+  ret.sort_unstable_by_key(|o| o.ids);
+  ret
+}
+
+impl Nfa {
+  // see `Overlap`, all the accept ids are those in this nfa
+  pub fn overlaps(&self) -> Vec<Overlap> {
+    let (raw, sets) = Dfa::from_nfa_with_sets(self);
+    overlaps(self, &raw, &sets, |id| id)
+  }
+}
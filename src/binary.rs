@@ -0,0 +1,116 @@
+use std::{fmt, convert::TryInto};
+use crate::*;
+
+// the binary format, all integers are u32 in little endian:
+// magic (8 bytes), version, ec_num, state_num, checksum, ec (256 bytes),
+// table (state_num * ec_num integers, the same as `DenseDfa::table`), acc (state_num integers, `NO_ACC` for None)
+// checksum is the 32-bit FNV-1a hash of version, ec_num, state_num and all the bytes after it
+pub const MAGIC: &[u8; 8] = b"re2dfa\0\0";
+pub const VERSION: u32 = 1;
+const HEADER_LEN: usize = 24;
+const NO_ACC: u32 = !0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryError {
+  BadMagic,
+  UnsupportedVersion(u32),
+  // the length of the buffer doesn't match the sizes in the header
+  BadLength,
+  BadChecksum,
+  // the checksum matches, but the content violates the invariants, e.g., an edge to a state that doesn't exist
+  Malformed,
+}
+
+impl fmt::Display for BinaryError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      BinaryError::BadMagic => f.write_str("not a serialized dfa"),
+      BinaryError::UnsupportedVersion(v) => write!(f, "unsupported version {}, expect {}", v, VERSION),
+      BinaryError::BadLength => f.write_str("length mismatch"),
+      BinaryError::BadChecksum => f.write_str("checksum mismatch"),
+      BinaryError::Malformed => f.write_str("malformed dfa"),
+    }
+  }
+}
+
+impl std::error::Error for BinaryError {}
+
+// the checksum of a buffer with a complete header, the magic and the checksum itself are skipped
+fn fnv1a(data: &[u8]) -> u32 {
+  data[8..HEADER_LEN - 4].iter().chain(&data[HEADER_LEN..]).fold(0x811c9dc5, |h, &b| (h ^ b as u32).wrapping_mul(0x01000193))
+}
+
+#[inline(always)]
+fn read(data: &[u8], idx: usize) -> u32 { u32::from_le_bytes(data[idx * 4..idx * 4 + 4].try_into().unwrap()) }
+
+impl Dfa {
+  // `self` should be minimized, see `DenseDfa::from_dfa`
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let dense = DenseDfa::from_dfa(self);
+    let mut ret = Vec::with_capacity(HEADER_LEN + 256 + (dense.table.len() + dense.acc.len()) * 4);
+    ret.extend_from_slice(MAGIC);
+    for x in [VERSION, dense.ec_num as u32, dense.acc.len() as u32, 0] { ret.extend_from_slice(&x.to_le_bytes()); }
+    ret.extend_from_slice(&dense.ec);
+    for &x in dense.table.iter() { ret.extend_from_slice(&x.to_le_bytes()); }
+    for &x in dense.acc.iter() { ret.extend_from_slice(&x.unwrap_or(NO_ACC).to_le_bytes()); }
+    let checksum = fnv1a(&ret);
+    ret[HEADER_LEN - 4..HEADER_LEN].copy_from_slice(&checksum.to_le_bytes());
+    ret
+  }
+}
+
+// a view of a dfa serialized by `Dfa::to_bytes`, it reads the buffer directly without copying,
+// so it can be used on a buffer with any alignment, e.g., from `include_bytes!`
+#[derive(Debug, Clone, Copy)]
+pub struct DfaRef<'a> {
+  pub ec_num: usize,
+  pub state_num: usize,
+  pub ec: &'a [u8; 256],
+  // `state_num * ec_num` integers in little endian
+  pub table: &'a [u8],
+  // `state_num` integers in little endian
+  pub acc: &'a [u8],
+}
+
+impl<'a> DfaRef<'a> {
+  // check all the invariants, so matching on the result never panics
+  pub fn from_bytes(data: &'a [u8]) -> Result<DfaRef<'a>, BinaryError> {
+    if data.len() < HEADER_LEN || &data[..8] != MAGIC { return Err(BinaryError::BadMagic); }
+    let header = &data[8..HEADER_LEN];
+    let (version, ec_num, state_num, checksum) = (read(header, 0), read(header, 1) as usize, read(header, 2) as usize, read(header, 3));
+    if version != VERSION { return Err(BinaryError::UnsupportedVersion(version)); }
+    let table_len = state_num.checked_mul(ec_num).and_then(|x| x.checked_mul(4)).ok_or(BinaryError::BadLength)?;
+    let len = state_num.checked_mul(4).and_then(|x| x.checked_add(table_len)).and_then(|x| x.checked_add(HEADER_LEN + 256));
+    if len != Some(data.len()) { return Err(BinaryError::BadLength); }
+    if fnv1a(data) != checksum { return Err(BinaryError::BadChecksum); }
+    let (ec, rest) = data[HEADER_LEN..].split_at(256);
+    let (table, acc) = rest.split_at(table_len);
+    let ret = DfaRef { ec_num, state_num, ec: ec.try_into().unwrap(), table, acc };
+    let valid = state_num != 0 && (1..=256).contains(&ec_num) && ret.ec.iter().all(|&k| (k as usize) < ec_num)
+      && (0..state_num * ec_num).all(|i| { let x = read(table, i); x == DEAD || (x as usize) < state_num });
+    if valid { Ok(ret) } else { Err(BinaryError::Malformed) }
+  }
+
+  // copy it into a `Dfa`
+  pub fn to_dfa(&self) -> Dfa {
+    let nodes = (0..self.state_num as u32).map(|state| {
+      let mut link = HashMap::default();
+      for k in 0..self.ec_num {
+        let out = read(self.table, state as usize * self.ec_num + k);
+        if out != DEAD { link.insert(k as u8, out); }
+      }
+      (self.acc(state), link)
+    }).collect();
+    Dfa { nodes, ec_num: self.ec_num, ec: *self.ec }
+  }
+}
+
+impl Automaton for DfaRef<'_> {
+  fn next(&self, state: u32, ch: u8) -> Option<u32> {
+    match read(self.table, state as usize * self.ec_num + self.ec[ch as usize] as usize) { DEAD => None, next => Some(next) }
+  }
+
+  fn acc(&self, state: u32) -> Option<u32> {
+    match read(self.acc, state as usize) { NO_ACC => None, id => Some(id) }
+  }
+}
@@ -0,0 +1,128 @@
+use std::{cmp::Reverse, collections::VecDeque};
+use crate::*;
+
+// the maximum length of a chain of default states, so that a step of matching visits at most this many rows
+const MAX_DEPTH: usize = 8;
+// the number of recently used rows that are tried as the default of a row, like the prototype queue of flex
+const PROTO_NUM: usize = 32;
+
+// a `Dfa` stored in a comb-vector (row displacement) table like flex and yacc, which is much smaller than `DenseDfa`
+// when many rows are similar, at the cost of possibly following several default states in each step of matching
+// the row of `state` only stores the edges that differ from the row of `default[state]`, and the rows are overlapped:
+// if `check[base[state] + k] == state`, the next state after reading class k is `next[base[state] + k]` (maybe `DEAD`),
+// otherwise it is the same as that of `default[state]`, or `DEAD` if `default[state] == DEAD`
+// a valid CombDfa should have base.len() == default.len() == acc.len() >= 1, and next.len() == check.len() >= max(base) + ec_num
+pub struct CombDfa {
+  pub base: Box<[u32]>,
+  // `default[state] < state`, so following them always terminates
+  pub default: Box<[u32]>,
+  pub next: Box<[u32]>,
+  // `DEAD` for unused slots
+  pub check: Box<[u32]>,
+  pub acc: Box<[Option<u32>]>,
+  pub ec_num: usize,
+  pub ec: [u8; 256],
+}
+
+// the first free slot not before `i`, `link[i] == i` iff slot i is free, otherwise slots in [i, link[i]) are used
+// the slots after the end are all free, the paths are halved when they are followed
+fn first_free(link: &mut [usize], mut i: usize) -> usize {
+  while i < link.len() && link[i] != i {
+    if link[i] < link.len() { link[i] = link[link[i]]; }
+    i = link[i];
+  }
+  i
+}
+
+impl CombDfa {
+  // `dfa` should be minimized, see `DenseDfa::from_dfa`
+  pub fn from_dfa(dfa: &Dfa) -> CombDfa {
+    let DenseDfa { table, acc, ec_num, ec } = DenseDfa::from_dfa(dfa);
+    let n = acc.len();
+    let row = |s: usize| &table[s * ec_num..(s + 1) * ec_num];
+    let (mut default, mut depth) = (vec![DEAD; n], vec![0; n]);
+    // the first state of each distinct row, and the most recently used rows, the most recent one first
+    let (mut first, mut protos) = (HashMap::default(), VecDeque::with_capacity(PROTO_NUM + 1));
+    // entries[s] is the classes that must be stored in the row of s
+    let mut entries = Vec::with_capacity(n);
+    for s in 0..n {
+      // choose the previous state whose row differs from that of s in the fewest classes,
+      // among a previous state with the same row and the recently used rows
+      let mut best = (row(s).iter().filter(|&&x| x != DEAD).count(), DEAD);
+      let same = *first.entry(row(s)).or_insert(s);
+      let candidates = protos.iter().copied().chain(Some(same).filter(|&d| d != s));
+      for d in candidates.filter(|&d| depth[d] < MAX_DEPTH) {
+        let diff = row(s).iter().zip(row(d)).filter(|(x, y)| x != y).count();
+        if diff < best.0 { best = (diff, d as u32); }
+      }
+      let (_, d) = best;
+      let es = if d == DEAD {
+        (0..ec_num).filter(|&k| row(s)[k] != DEAD).collect::<Vec<_>>()
+      } else {
+        default[s] = d;
+        depth[s] = depth[d as usize] + 1;
+        protos.retain(|&p| p != d as usize);
+        protos.push_front(d as usize);
+        (0..ec_num).filter(|&k| row(s)[k] != row(d as usize)[k]).collect()
+      };
+      // a row identical to its default is not a useful prototype
+      if !es.is_empty() { protos.push_front(s); }
+      protos.truncate(PROTO_NUM);
+      entries.push(es);
+    }
+    // put the rows with more entries first, and each row at the first position where it fits
+    // `link` finds the first free slot not before a given one, so that used slots are skipped quickly
+    let mut order = (0..n).collect::<Vec<_>>();
+    order.sort_by_key(|&s| Reverse(entries[s].len()));
+    let (mut base, mut next, mut check) = (vec![0; n], vec![DEAD; ec_num], vec![DEAD; ec_num]);
+    let mut link = (0..ec_num).collect::<Vec<_>>();
+    for s in order {
+      let es = &entries[s];
+      if es.is_empty() { continue; }
+      let mut b = 0;
+      loop {
+        b = first_free(&mut link, b + es[0]) - es[0];
+        if es[1..].iter().all(|&k| check.get(b + k).copied().unwrap_or(DEAD) == DEAD) { break; }
+        b += 1;
+      }
+      if check.len() < b + ec_num {
+        link.extend(check.len()..b + ec_num);
+        check.resize(b + ec_num, DEAD);
+        next.resize(b + ec_num, DEAD);
+      }
+      for &k in es {
+        check[b + k] = s as u32;
+        next[b + k] = row(s)[k];
+        link[b + k] = b + k + 1;
+      }
+      base[s] = b as u32;
+    }
+    CombDfa { base: base.into(), default: default.into(), next: next.into(), check: check.into(), acc, ec_num, ec }
+  }
+
+  // return the next state of `state` after reading `ch`, or `DEAD`
+  #[inline(always)]
+  pub fn next(&self, mut state: u32, ch: u8) -> u32 {
+    let k = self.ec[ch as usize] as usize;
+    loop {
+      let i = self.base[state as usize] as usize + k;
+      if self.check[i] == state { return self.next[i]; }
+      state = self.default[state as usize];
+      if state == DEAD { return DEAD; }
+    }
+  }
+
+  // the number of entries in base, default, next and check
+  pub fn size(&self) -> usize { self.base.len() * 2 + self.next.len() * 2 }
+
+  // the compression ratio, i.e., `size()` divided by the number of entries in the dense table
+  pub fn ratio(&self) -> f64 { self.size() as f64 / (self.acc.len() * self.ec_num) as f64 }
+}
+
+impl Automaton for CombDfa {
+  fn next(&self, state: u32, ch: u8) -> Option<u32> {
+    match CombDfa::next(self, state, ch) { DEAD => None, next => Some(next) }
+  }
+
+  fn acc(&self, state: u32) -> Option<u32> { self.acc[state as usize] }
+}
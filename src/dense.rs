@@ -0,0 +1,34 @@
+use crate::*;
+
+// the sentinel in `DenseDfa::table` meaning there is no outgoing edge, i.e., matching should stop
+pub const DEAD: u32 = !0;
+
+// a `Dfa` stored in a flat transition table, so each step of matching is an array indexing instead of a hash lookup
+// a valid DenseDfa should have acc.len() >= 1, and table.len() == acc.len() * ec_num
+pub struct DenseDfa {
+  // `table[state * ec_num + ec[ch]]` is the next state of `state` after reading `ch`, or `DEAD`
+  pub table: Box<[u32]>,
+  // `acc[state]` has the same meaning as `Dfa::nodes[state].0`
+  pub acc: Box<[Option<u32>]>,
+  pub ec_num: usize,
+  pub ec: [u8; 256],
+}
+
+impl DenseDfa {
+  // `dfa` should be minimized, otherwise its dead state is kept as a normal state, and matching can't stop early
+  pub fn from_dfa(dfa: &Dfa) -> DenseDfa {
+    let ec_num = dfa.ec_num;
+    let mut table = vec![DEAD; dfa.nodes.len() * ec_num];
+    for ((_, edges), row) in dfa.nodes.iter().zip(table.chunks_exact_mut(ec_num)) {
+      for (&k, &out) in edges { row[k as usize] = out; }
+    }
+    let acc = dfa.nodes.iter().map(|&(acc, _)| acc).collect();
+    DenseDfa { table: table.into(), acc, ec_num, ec: dfa.ec }
+  }
+
+  // return the next state of `state` after reading `ch`, or `DEAD`
+  #[inline(always)]
+  pub fn next(&self, state: u32, ch: u8) -> u32 {
+    self.table[state as usize * self.ec_num + self.ec[ch as usize] as usize]
+  }
+}
@@ -0,0 +1,170 @@
+use crate::*;
+
+// regexes are kept in a canonical form, so that similar regexes (i.e., equal under the associativity, commutativity
+// and idempotence of `|`, `&`, and some other simple rules) are identical, then there are finitely many derivatives
+// the canonical form of the empty language is an empty `DisjunctionCh`, and there is no `Ch`
+
+fn null() -> Re { DisjunctionCh([0; 8].into()) }
+
+fn is_null(r: &Re) -> bool { matches!(r, DisjunctionCh(s) if **s == [0; 8]) }
+
+// the language of all strings
+fn any() -> Re { Kleene(Box::new(DisjunctionCh([!0; 8].into()))) }
+
+fn concat(a: Re, b: Re) -> Re {
+  if is_null(&a) || is_null(&b) { return null(); }
+  match (a, b) {
+    (Eps, x) | (x, Eps) => x,
+    (a, b) => {
+      let mut c = Vec::new();
+      for x in [a, b] {
+        match x { Concat(x) => c.extend(x.into_vec()), x => c.push(x) }
+      }
+      Concat(c.into())
+    }
+  }
+}
+
+// all the char sets are merged into one, nested `Disjunction`s are flattened, and the children are sorted and deduplicated
+fn alt(xs: impl IntoIterator<Item=Re>) -> Re {
+  let (mut set, mut d) = ([0; 8], Vec::new());
+  let mut stack = xs.into_iter().collect::<Vec<_>>();
+  while let Some(x) = stack.pop() {
+    match x {
+      Disjunction(x) => stack.extend(x.into_vec()),
+      Ch(ch) => { bitset::bs(&mut set).set(ch as usize); }
+      DisjunctionCh(s) => { bitset::bs(&mut set).or(s.as_ref()); }
+      x => d.push(x),
+    }
+  }
+  if set != [0; 8] { d.push(DisjunctionCh(set.into())); }
+  if d.contains(&any()) { return any(); }
+  d.sort_unstable();
+  d.dedup();
+  match d.len() { 0 => null(), 1 => d.remove(0), _ => Disjunction(d.into()) }
+}
+
+// the dual of `alt`
+fn and(xs: impl IntoIterator<Item=Re>) -> Re {
+  let (mut set, mut has_set, mut d) = ([!0; 8], false, Vec::new());
+  let mut stack = xs.into_iter().collect::<Vec<_>>();
+  while let Some(x) = stack.pop() {
+    let s = match x {
+      Intersection(x) => {
+        stack.extend(x.into_vec());
+        continue;
+      }
+      Ch(ch) => {
+        let mut s = [0; 8];
+        bitset::bs(&mut s).set(ch as usize);
+        s
+      }
+      DisjunctionCh(s) => *s,
+      x => {
+        if x != any() { d.push(x); }
+        continue;
+      }
+    };
+    for (x, y) in set.iter_mut().zip(s.iter()) { *x &= y; }
+    has_set = true;
+  }
+  if has_set {
+    if set == [0; 8] { return null(); }
+    d.push(DisjunctionCh(set.into()));
+  }
+  d.sort_unstable();
+  d.dedup();
+  match d.len() { 0 => any(), 1 => d.remove(0), _ => Intersection(d.into()) }
+}
+
+fn not(r: Re) -> Re {
+  match r {
+    Complement(x) => *x,
+    r if is_null(&r) => any(),
+    r if r == any() => null(),
+    r => Complement(Box::new(r)),
+  }
+}
+
+// whether `r` accepts the empty string
+fn nullable(r: &Re) -> bool {
+  match r {
+    Eps | Kleene(_) => true,
+    Ch(_) | DisjunctionCh(_) => false,
+    Concat(x) | Intersection(x) => x.iter().all(nullable),
+    Disjunction(x) => x.iter().any(nullable),
+    Complement(x) => !nullable(x),
+  }
+}
+
+// the derivative of `r` with respect to `ch`, i.e., { s | ch s is accepted by r }, in canonical form
+fn deriv(r: &Re, ch: u8) -> Re {
+  match r {
+    Eps => null(),
+    &Ch(x) => if x == ch { Eps } else { null() },
+    DisjunctionCh(s) => if unsafe { bitset::ubs(s.as_ref()).get(ch as usize) } { Eps } else { null() },
+    Concat(x) => {
+      let rest = match x.len() { 1 => Eps, 2 => x[1].clone(), _ => Concat(x[1..].into()) };
+      let d = concat(deriv(&x[0], ch), rest.clone());
+      if nullable(&x[0]) { alt([d, deriv(&rest, ch)]) } else { d }
+    }
+    Disjunction(x) => alt(x.iter().map(|x| deriv(x, ch))),
+    Kleene(x) => concat(deriv(x, ch), r.clone()),
+    Complement(x) => not(deriv(x, ch)),
+    Intersection(x) => and(x.iter().map(|x| deriv(x, ch))),
+  }
+}
+
+impl Dfa {
+  // build a dfa directly from `re` with Brzozowski derivatives, without nfa
+  // a state is a list of regexes, the i-th of which is a derivative of `re[i]`, and an edge via class k goes to their
+  // derivatives with respect to any char in class k, the state where all regexes are empty is the dead state, which is omitted
+  // the result is often close to minimal, but it can still be minimized
+  pub fn from_re(re: &[Re]) -> Dfa {
+    match Dfa::from_re_limited(re, &Limits::default()) {
+      Ok(dfa) => dfa,
+      Err(_) => unreachable!(),
+    }
+  }
+
+  // like `from_re`, but stop as soon as the number of states or the estimated memory exceeds `limits`
+  // the memory is estimated by the total size of the regexes in the states
+  pub fn from_re_limited(re: &[Re], limits: &Limits) -> Result<Dfa, LimitError> {
+    let (ec_num, ec) = nfa::compute_ec(re);
+    let repr = analysis::ec_repr(&ec, ec_num);
+    let mut states = vec![Box::<[Re]>::from(re)];
+    let mut ids = HashMap::default();
+    ids.insert(states[0].clone(), 0);
+    let mut nodes = Vec::new();
+    let size = |s: &[Re]| s.iter().map(Re::size).sum::<usize>() * std::mem::size_of::<Re>() * 2 + ec_num * 16;
+    let mut mem = size(re);
+    while nodes.len() < states.len() {
+      let cur = std::mem::take(&mut states[nodes.len()]);
+      let mut link = HashMap::default();
+      for (k, &ch) in repr.iter().enumerate() {
+        let next = cur.iter().map(|r| deriv(r, ch)).collect::<Box<[Re]>>();
+        if next.iter().all(is_null) { continue; }
+        let id = states.len() as u32;
+        let id = *ids.entry(next).or_insert_with_key(|next| {
+          mem += size(next);
+          states.push(next.clone());
+          id
+        });
+        link.insert(k as u8, id);
+        if states.len() > limits.dfa_states || mem > limits.memory {
+          let kind = if states.len() > limits.dfa_states { LimitKind::DfaStates } else { LimitKind::Memory };
+          states[nodes.len()] = cur;
+          let mut involved = vec![0; re.len()];
+          for s in &states {
+            for (i, r) in s.iter().enumerate() { if !is_null(r) { involved[i] += 1; } }
+          }
+          return Err(LimitError { kind, patterns: limit::culprits(&involved) });
+        }
+      }
+      let id = cur.iter().position(nullable).map(|id| id as u32);
+      states[nodes.len()] = cur;
+      nodes.push((id, link));
+    }
+    Ok(Dfa { nodes, ec_num, ec })
+  }
+}
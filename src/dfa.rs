@@ -1,7 +1,8 @@
-use std::collections::VecDeque;
 use crate::*;
 
 type DfaNode = (Option<u32>, HashMap<u8, u32>);
+// a dfa, and the nfa state set (as a bitset) of each dfa state
+type DfaWithSets = (Dfa, Vec<Box<[u32]>>);
 
 // nodes[i].0 stands for node state(whether is terminal, and which nfa it belongs)
 // a valid Dfa should have nodes.len() >= 1
@@ -15,135 +16,187 @@ pub struct Dfa {
 
 impl Dfa {
   // the generated dfa contains a dead state, which will eliminated when minimizing it
-  pub fn from_nfa(nfa: &Nfa) -> Dfa {
-    let (ec_num, nfa_node, nfa_e_close) = (nfa.ec_num, nfa.nodes.as_ptr(), nfa.e_close.as_ptr());
+  pub fn from_nfa(nfa: &Nfa) -> Dfa { Dfa::from_nfa_with_sets(nfa).0 }
+
+  // also return the nfa state set (as a bitset) of each dfa state
+  // dfa states are numbered in bfs order, so the first state found to have some property is the closest to start
+  pub fn from_nfa_with_sets(nfa: &Nfa) -> DfaWithSets {
+    match Dfa::from_nfa_limited(nfa, &Limits::default()) {
+      Ok(x) => x,
+      Err(_) => unreachable!(),
+    }
+  }
+
+  // like `from_nfa_with_sets`, but stop as soon as the number of states or the estimated memory exceeds `limits`
+  pub fn from_nfa_limited(nfa: &Nfa, limits: &Limits) -> Result<DfaWithSets, LimitError> {
+    let ec_num = nfa.ec_num;
     let elem_len = bitset::bslen(nfa.nodes.len());
 
-    assert!(elem_len != 0 && elem_len * nfa.nodes.len() == nfa.e_close.len());
+    // an empty `e_close` means the eps closures are computed on demand
+    assert!(elem_len != 0 && (nfa.e_close.is_empty() || elem_len * nfa.nodes.len() == nfa.e_close.len()));
 
     let mut tmp = Box::<[u32]>::from(vec![0; elem_len]);
+    let mut stack = Vec::new();
     let mut ss = HashMap::default();
-    let mut q = VecDeque::new();
+    // `sets[nodes.len()..]` is the bfs queue
+    let mut sets = Vec::new();
+    // each state takes 2 copies of its set (in `ss` and `sets`), and a `link` with at most `ec_num` entries
+    let (fixed, per_state) = (nfa.e_close.len() * 4, elem_len * 8 + ec_num * 16);
 
-    // eps closure of nfa node 0
-    let start = Box::<[u32]>::from(unsafe { std::slice::from_raw_parts(nfa_e_close, elem_len) });
+    let start = nfa.start_set(&mut stack);
     ss.insert(start.clone(), 0);
-    q.push_back(start);
+    sets.push(start);
 
     let mut nodes = Vec::new();
-    while let Some(cur) = q.pop_front() {
-      let cur = bitset::ibs(&cur);
+    while nodes.len() < sets.len() {
+      // take it out temporarily, because new sets will be pushed into `sets` below
+      let cur_set = std::mem::take(&mut sets[nodes.len()]);
       let mut link = HashMap::default();
       for k in 0..ec_num {
-        bitset::bs(&mut tmp).clear();
-        cur.ones(|i| unsafe {
-          if let Some(outs) = (*nfa_node.add(i)).edges.get(&(k as u8)) {
-            for &out in outs {
-              bitset::ubs(&*tmp).or(nfa_e_close.add(out as usize * elem_len), elem_len);
-            }
-          }
-        });
+        nfa.step(&cur_set, k as u8, &mut tmp, &mut stack);
         let id = ss.len() as u32;
         let id = *ss.entry(tmp.clone()).or_insert_with(|| {
-          q.push_back(tmp.clone());
+          sets.push(tmp.clone());
           id
         });
         link.insert(k as u8, id);
+        if sets.len() > limits.dfa_states || fixed + sets.len() * per_state > limits.memory {
+          let kind = if sets.len() > limits.dfa_states { LimitKind::DfaStates } else { LimitKind::Memory };
+          sets[nodes.len()] = cur_set;
+          return Err(nfa.subset_limit_error(kind, &sets));
+        }
       }
-      let mut id = None;
-      cur.ones(|i| unsafe { if id.is_none() { id = (*nfa_node.add(i)).id; } });
+      let id = nfa.set_acc(&cur_set);
+      sets[nodes.len()] = cur_set;
       nodes.push((id, link));
     }
-    Dfa { nodes, ec_num, ec: nfa.ec }
+    Ok((Dfa { nodes, ec_num, ec: nfa.ec }, sets))
   }
 
+  // Hopcroft's partition refinement algorithm, O(n * ec_num * log n) time and O(n * ec_num) memory
+  // the initial partition groups nodes by their acc id
+  // a missing edge is regarded as an edge to a virtual dead state, all nodes equivalent to it are removed in the result
+  // the order of nodes is kept, i.e., the node with smaller old index has smaller new index, so start state is still 0
   pub fn minimize(&mut self) {
     assert!(!self.nodes.is_empty());
 
-    let n = self.nodes.len();
-    let mut rev_edges = vec![HashMap::default(); n];
-    let rev_edges = rev_edges.as_mut_ptr();
+    const INVALID: u32 = !0;
+    let (n, ec_num) = (self.nodes.len(), self.ec_num);
+    // `dead` is the virtual dead state, there are n1 states in total
+    let (dead, n1) = (n, n + 1);
+    let mut trans = vec![dead as u32; n1 * ec_num];
     for (i, (_, edges)) in self.nodes.iter().enumerate() {
-      for (&k, &out) in edges {
-        unsafe { (*rev_edges.add(out as usize)).entry(k).or_insert(Vec::new()).push(i as u32); }
-      }
+      for (&k, &out) in edges { trans[i * ec_num + k as usize] = out; }
     }
-    let dp = bitset::bsmake(n * n); // only access upper part, i.e., should guarantee i < j if access it with i * n + j
-    let dp = unsafe { bitset::ubs(&*dp) };
-    let mut q = VecDeque::new();
-    for (i, &(id1, _)) in self.nodes.iter().enumerate() {
-      for (j, &(id2, _)) in self.nodes.iter().enumerate().skip(i) {
-        if id1 != id2 {
-          dp.set(i * n + j);
-          q.push_back((i as u32, j as u32));
-        }
+    // reverse edges in CSR format: rev[rev_start[k * n1 + j]..rev_start[k * n1 + j + 1]] are the states that go to j via k
+    let mut rev_start = vec![0; ec_num * n1 + 1];
+    for outs in trans.chunks_exact(ec_num) {
+      for (k, &out) in outs.iter().enumerate() { rev_start[k * n1 + out as usize + 1] += 1; }
+    }
+    for i in 1..rev_start.len() { rev_start[i] += rev_start[i - 1]; }
+    let mut rev = vec![0; ec_num * n1];
+    let mut fill = rev_start.clone();
+    for (i, outs) in trans.chunks_exact(ec_num).enumerate() {
+      for (k, &out) in outs.iter().enumerate() {
+        let f = &mut fill[k * n1 + out as usize];
+        rev[*f as usize] = i as u32;
+        *f += 1;
       }
     }
-    while let Some((i, j)) = q.pop_front() {
-      let (rev_i, rev_j) = unsafe { (&*rev_edges.add(i as usize), &*rev_edges.add(j as usize)) };
-      for (k_i, out_i) in rev_i {
-        if let Some(out_j) = rev_j.get(k_i) {
-          for &i in out_i {
-            for &j in out_j {
-              let (i, j) = if i < j { (i, j) } else { (j, i) };
-              if !dp.get(i as usize * n + j as usize) {
-                dp.set(i as usize * n + j as usize);
-                q.push_back((i, j));
-              }
-            }
-          }
-        }
+
+    // the partition: block `b` contains elems[b_start[b]..b_end[b]], and `loc` is the inverse of `elems`
+    let mut block = vec![0; n1];
+    let (mut b_start, mut b_end) = (Vec::new(), Vec::new());
+    {
+      let mut acc2block = HashMap::default();
+      for (i, b) in block.iter_mut().enumerate() {
+        let acc = if i == dead { None } else { self.nodes[i].0 };
+        let new = acc2block.len() as u32;
+        *b = *acc2block.entry(acc).or_insert(new);
       }
+      b_start.resize(acc2block.len(), 0);
+      for &b in &block { b_start[b as usize] += 1; }
+      let mut sum = 0;
+      for x in &mut b_start { sum += *x; *x = sum - *x; }
+      b_end.extend_from_slice(&b_start);
+    }
+    let mut elems = vec![0; n1];
+    let mut loc = vec![0; n1];
+    for i in 0..n1 {
+      let e = &mut b_end[block[i] as usize];
+      elems[*e as usize] = i as u32;
+      loc[i] = *e;
+      *e += 1;
     }
+    let mut marked = vec![0; b_start.len()];
+    // it is enough to use all blocks except one as the initial splitters
+    let largest = (0..b_start.len()).max_by_key(|&b| b_end[b] - b_start[b]).unwrap() as u32;
+    let mut work = (0..b_start.len() as u32).filter(|&b| b != largest).collect::<Vec<_>>();
 
-    const INVALID: u32 = !0;
-    let mut ids = vec![INVALID; n];
-    let mut id2old = Vec::with_capacity(n);
-    let mut q = VecDeque::with_capacity(n);
-    // if there is no node, we can't delete this node, this is the requirement of Dfa (nodes.len() >= 1)
-    let dead_node = if n == 1 { None } else {
-      self.nodes.iter().enumerate().position(|(i, node)|
-        node.0.is_none() && node.1.iter().all(|(_, &out)| out == i as u32))
-    };
-    for i in 0..n {
-      unsafe {
-        if dead_node != Some(i) && *ids.get_unchecked(i) == INVALID {
-          let id = id2old.len() as u32;
-          *ids.get_unchecked_mut(i) = id;
-          q.push_back(i);
-          let mut old = vec![i as u32];
-          while let Some(cur) = q.pop_front() {
-            for j in cur..n {
-              if *ids.get_unchecked(j) == INVALID {
-                if !dp.get(cur * n + j) {
-                  *ids.get_unchecked_mut(j) = id;
-                  q.push_back(j);
-                  old.push(j as u32);
-                }
-              }
-            }
+    let (mut splitter, mut touched) = (Vec::new(), Vec::new());
+    while let Some(b) = work.pop() {
+      splitter.clear();
+      splitter.extend_from_slice(&elems[b_start[b as usize] as usize..b_end[b as usize] as usize]);
+      for k in 0..ec_num {
+        // move all the states that go into `splitter` via k to the front of their blocks
+        for &t in &splitter {
+          let idx = k * n1 + t as usize;
+          for &p in &rev[rev_start[idx] as usize..rev_start[idx + 1] as usize] {
+            let bp = block[p as usize] as usize;
+            let (m, lp) = (b_start[bp] + marked[bp], loc[p as usize]);
+            let q = elems[m as usize];
+            elems.swap(m as usize, lp as usize);
+            loc[q as usize] = lp;
+            loc[p as usize] = m;
+            if marked[bp] == 0 { touched.push(bp); }
+            marked[bp] += 1;
+          }
+        }
+        for y in touched.drain(..) {
+          let (s, e, m) = (b_start[y], b_end[y], std::mem::replace(&mut marked[y], 0));
+          if m == e - s { continue; }
+          // the new block is the smaller part, so each state changes its block O(log n) times
+          let new = b_start.len();
+          if m <= e - s - m {
+            b_start.push(s);
+            b_end.push(s + m);
+            b_start[y] = s + m;
+          } else {
+            b_start.push(s + m);
+            b_end.push(e);
+            b_end[y] = s + m;
           }
-          id2old.push(old);
+          marked.push(0);
+          for &x in &elems[b_start[new] as usize..b_end[new] as usize] { block[x as usize] = new as u32; }
+          // whether `y` is in `work` or not, adding the smaller part is enough
+          work.push(new as u32);
         }
       }
     }
 
-    let mut nodes = Vec::new();
-    for old in id2old {
-      unsafe {
-        let mut link = HashMap::default();
-        // they must have the same acc, so pick the acc of old[0]
-        let acc = self.nodes.get_unchecked(*old.get_unchecked(0) as usize).0;
-        for o in old {
-          for (&k, &out) in &self.nodes.get_unchecked(o as usize).1 {
-            if dead_node != Some(out as usize) {
-              link.insert(k, *ids.get_unchecked(out as usize));
-            }
-          }
-        }
-        nodes.push((acc, link));
+    let dead_block = block[dead];
+    if block[0] == dead_block {
+      // the dfa accepts nothing, but it still requires nodes.len() >= 1
+      self.nodes = vec![(None, HashMap::default())];
+      return;
+    }
+    let mut ids = vec![INVALID; b_start.len()];
+    let mut reps = Vec::new();
+    for (i, &b) in block[..n].iter().enumerate() {
+      let b = b as usize;
+      if b != dead_block as usize && ids[b] == INVALID {
+        ids[b] = reps.len() as u32;
+        reps.push(i);
       }
     }
-    self.nodes = nodes;
+    // they must have the same acc and the same edges (in terms of blocks), so pick a representative
+    self.nodes = reps.iter().map(|&r| {
+      let mut link = HashMap::default();
+      for (k, &out) in trans[r * ec_num..(r + 1) * ec_num].iter().enumerate() {
+        let b = block[out as usize];
+        if b != dead_block { link.insert(k as u8, ids[b as usize]); }
+      }
+      (self.nodes[r].0, link)
+    }).collect();
   }
 }
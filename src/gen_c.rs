@@ -0,0 +1,180 @@
+use std::fmt::{self, Display, Formatter};
+use crate::{*, gen_rust::{uint_ty, write_list}};
+
+// the smallest C unsigned integer type that can hold 0..=max
+fn c_ty(max: usize) -> &'static str {
+  match uint_ty(max) { "u8" => "uint8_t", "u16" => "uint16_t", _ => "uint32_t" }
+}
+
+// a C string literal of `s`, rust escapes like `\u{e9}` and `\'` are not valid C, so bytes other than printable ascii
+// are written as 3-digit octal escapes, which can't absorb the following chars, and `?` is escaped to avoid trigraphs
+fn c_str(s: &str) -> impl Display + '_ {
+  fn2display(move |f| {
+    f.write_str("\"")?;
+    for &ch in s.as_bytes() {
+      match ch {
+        b'"' | b'\\' | b'?' => write!(f, "\\{}", ch as char)?,
+        b' '..=b'~' => write!(f, "{}", ch as char)?,
+        _ => write!(f, "\\{:03o}", ch)?,
+      }
+    }
+    f.write_str("\"")
+  })
+}
+
+// whether `s` can be used in a C identifier after a prefix like `LEXER_`
+fn c_ident(s: &str) -> bool {
+  !s.is_empty() && s.bytes().all(|ch| ch.is_ascii_alphanumeric() || ch == b'_')
+}
+
+impl Dfa {
+  // generate a C header and source pair, which only depend on the C99 standard library
+  // `name` is the file name of the header without ".h", and the prefix of all the exported identifiers, so it should be a C identifier
+  // the header declares `int {name}_next_token(const uint8_t *input, size_t len, uint32_t *id, size_t *token_len)`,
+  // which has the same meaning as `Automaton::longest_match`: if it returns 1, the longest non-empty prefix of `input`
+  // has length `*token_len`, and is accepted by pattern `*id`; if it returns 0, no prefix is accepted
+  // if `tokens` is not None, `self` should be built from it, and the header also declares a macro `{NAME}_{token}` for each token id,
+  // `{NAME}_TOKEN_NUM`, and `{name}_names`, `{name}_skip` for the name and skip flag of each token, where `NAME` is `name` in upper case
+  // the macro is omitted for a token whose name has chars other than ascii alphanumerics and `_`, its name is still in `{name}_names`
+  pub fn gen_c<'a>(&'a self, name: &'a str, tokens: Option<&'a TokenSet>, enc: Encoding) -> (impl Display + 'a, impl Display + 'a) {
+    let header = fn2display(move |f| {
+      let upper = name.to_uppercase();
+      writeln!(f, "// generated by re2dfa, do not edit\n")?;
+      writeln!(f, "#ifndef {}_H\n#define {0}_H\n", upper)?;
+      f.write_str("#include <stddef.h>\n#include <stdint.h>\n\n#ifdef __cplusplus\nextern \"C\" {\n#endif\n\n")?;
+      if let Some(tokens) = tokens {
+        writeln!(f, "#define {}_TOKEN_NUM {}", upper, tokens.tokens.len())?;
+        for (id, t) in tokens.tokens.iter().enumerate().filter(|(_, t)| c_ident(&t.name)) {
+          writeln!(f, "#define {}_{} {}", upper, t.name, id)?;
+        }
+        writeln!(f, "extern const char *const {}_names[{}_TOKEN_NUM];", name, upper)?;
+        writeln!(f, "extern const unsigned char {}_skip[{}_TOKEN_NUM];\n", name, upper)?;
+      }
+      writeln!(f, "int {}_next_token(const uint8_t *input, size_t len, uint32_t *id, size_t *token_len);\n", name)?;
+      writeln!(f, "#ifdef __cplusplus\n}}\n#endif\n\n#endif")
+    });
+    let source = fn2display(move |f| {
+      let upper = name.to_uppercase();
+      let (n, ec_num) = (self.nodes.len(), self.ec_num);
+      let ty = c_ty(n);
+      writeln!(f, "// generated by re2dfa, do not edit\n")?;
+      writeln!(f, "#include \"{}.h\"\n", name)?;
+      writeln!(f, "#define EC_NUM {}\n#define STATE_NUM {}\n#define DEAD {}\n", ec_num, n, n)?;
+      if let Some(tokens) = tokens {
+        write!(f, "const char *const {}_names[{}_TOKEN_NUM] = {{", name, upper)?;
+        write_list(f, tokens.tokens.iter().map(|t| c_str(&t.name)), usize::MAX)?;
+        write!(f, "}};\nconst unsigned char {}_skip[{}_TOKEN_NUM] = {{", name, upper)?;
+        write_list(f, tokens.tokens.iter().map(|t| t.skip as u8), usize::MAX)?;
+        f.write_str("};\n\n")?;
+      }
+      f.write_str("static const uint8_t ec[256] = {\n  ")?;
+      write_list(f, self.ec.iter(), 16)?;
+      f.write_str("\n};\n\n")?;
+      match enc {
+        Encoding::Dense => {
+          writeln!(f, "static const {} table[STATE_NUM][EC_NUM] = {{", ty)?;
+          for (_, edges) in &self.nodes {
+            f.write_str("  {")?;
+            write_list(f, (0..ec_num).map(|k| edges.get(&(k as u8)).map_or(n, |&out| out as usize)), usize::MAX)?;
+            f.write_str("},\n")?;
+          }
+          f.write_str("};\n\n")?;
+        }
+        Encoding::Comb => {
+          // the same as the arrays in `gen_rust_encoded`
+          let comb = CombDfa::from_dfa(self);
+          let state = |x: &u32| if *x == DEAD { n } else { *x as usize };
+          writeln!(f, "// comb table: {} entries, {:.1}% of the dense table", comb.size(), comb.ratio() * 100.0)?;
+          writeln!(f, "#define COMB_LEN ({} + EC_NUM)\n", comb.next.len() - ec_num)?;
+          write!(f, "static const {} base[STATE_NUM] = {{\n  ", c_ty(comb.next.len()))?;
+          write_list(f, comb.base.iter(), 16)?;
+          write!(f, "\n}};\n\nstatic const {} def[STATE_NUM] = {{\n  ", ty)?;
+          write_list(f, comb.default.iter().map(state), 16)?;
+          write!(f, "\n}};\n\nstatic const {} next[COMB_LEN] = {{\n  ", ty)?;
+          write_list(f, comb.next.iter().map(state), 16)?;
+          write!(f, "\n}};\n\nstatic const {} check[COMB_LEN] = {{\n  ", ty)?;
+          write_list(f, comb.check.iter().map(state), 16)?;
+          f.write_str("\n};\n\n")?;
+          f.write_str(r#"// return the next state of `state` after reading a char in class `k`, or `DEAD`
+static size_t next_state(size_t state, size_t k) {
+  for (;;) {
+    size_t i = base[state] + k;
+    if (check[i] == state) return next[i];
+    if (def[state] == DEAD) return DEAD;
+    state = def[state];
+  }
+}
+
+"#)?;
+        }
+        Encoding::Direct => {}
+      }
+      // store id + 1 in `acc`, so that 0 means the state doesn't accept
+      let max_acc = self.nodes.iter().filter_map(|&(acc, _)| acc).max().map_or(0, |id| id as usize + 1);
+      write!(f, "static const {} acc[STATE_NUM] = {{\n  ", c_ty(max_acc))?;
+      write_list(f, self.nodes.iter().map(|&(acc, _)| acc.map_or(0, |id| id as usize + 1)), 16)?;
+      f.write_str("\n};\n\n")?;
+      let next = match enc {
+        Encoding::Dense => "table[state][ec[input[i]]]",
+        Encoding::Comb => "next_state(state, ec[input[i]])",
+        Encoding::Direct => return self.gen_c_direct(f, name),
+      };
+      write!(f, r#"int {}_next_token(const uint8_t *input, size_t len, uint32_t *id, size_t *token_len) {{
+  size_t state = 0, i;
+  int found = 0;
+  for (i = 0; i < len; i++) {{
+    size_t out = {};
+    if (out == DEAD) break;
+    state = out;
+    if (acc[state]) {{
+      *id = acc[state] - 1;
+      *token_len = i + 1;
+      found = 1;
+    }}
+  }}
+  return found;
+}}
+"#, name, next)
+    });
+    (header, source)
+  }
+
+  // the `{name}_next_token` function of `Encoding::Direct`, which switches on the state and then on the class
+  fn gen_c_direct(&self, f: &mut Formatter, name: &str) -> fmt::Result {
+    write!(f, r#"int {}_next_token(const uint8_t *input, size_t len, uint32_t *id, size_t *token_len) {{
+  size_t state = 0, i;
+  int found = 0;
+  for (i = 0; i < len; i++) {{
+    switch (state) {{
+"#, name)?;
+    for (idx, (_, edges)) in self.nodes.iter().enumerate() {
+      if edges.is_empty() {
+        writeln!(f, "    case {}: return found;", idx)?;
+        continue;
+      }
+      // the classes going to each state, sorted by the first class
+      let mut outs = HashMap::default();
+      for (&k, &out) in edges { outs.entry(out).or_insert_with(Vec::new).push(k); }
+      let mut outs = outs.into_iter().map(|(out, mut ks)| (ks.sort_unstable(), (ks, out)).1).collect::<Vec<_>>();
+      outs.sort_unstable();
+      writeln!(f, "    case {}:\n      switch (ec[input[i]]) {{", idx)?;
+      for (ks, out) in outs {
+        f.write_str("      ")?;
+        for k in ks { write!(f, "case {}: ", k)?; }
+        writeln!(f, "state = {}; break;", out)?;
+      }
+      if edges.len() != self.ec_num { f.write_str("      default: return found;\n")?; }
+      f.write_str("      }\n      break;\n")?;
+    }
+    f.write_str(r#"    }
+    if (acc[state]) {
+      *id = acc[state] - 1;
+      *token_len = i + 1;
+      found = 1;
+    }
+  }
+  return found;
+}
+"#)
+  }
+}
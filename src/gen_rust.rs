@@ -0,0 +1,181 @@
+use std::fmt::{self, Display, Formatter};
+use crate::*;
+
+// the smallest unsigned integer type that can hold 0..=max
+pub(crate) fn uint_ty(max: usize) -> &'static str {
+  if max <= u8::MAX as usize { "u8" } else if max <= u16::MAX as usize { "u16" } else { "u32" }
+}
+
+// a byte in a rust pattern, printable chars are shown as byte literals
+fn byte_pat(ch: u8) -> impl Display {
+  fn2display(move |f| match ch {
+    b'\\' | b'\'' => write!(f, "b'\\{}'", ch as char),
+    _ if ch.is_ascii_graphic() => write!(f, "b'{}'", ch as char),
+    _ => write!(f, "{:#04x}", ch),
+  })
+}
+
+// write `xs` separated by ", ", and break line every `per_line` elements
+pub(crate) fn write_list<T: Display>(f: &mut Formatter, xs: impl IntoIterator<Item=T>, per_line: usize) -> fmt::Result {
+  for (i, x) in xs.into_iter().enumerate() {
+    if i != 0 { f.write_str(if i % per_line == 0 { ",\n  " } else { ", " })?; }
+    write!(f, "{}", x)?;
+  }
+  Ok(())
+}
+
+impl Dfa {
+  // generate a self-contained rust module, which doesn't depend on re2dfa, it can be used like:
+  // `mod lexer { include!(concat!(env!("OUT_DIR"), "/lexer.rs")); }`, where `lexer.rs` is written by build.rs
+  // the module contains the ec table, the transition table compressed by `Encoding::Comb`, the accept array and a `next_token` function
+  // in transition table, state `nodes.len()` (represented by `DEAD`) means there is no outgoing edge
+  pub fn gen_rust<'a>(&'a self) -> impl Display + 'a { self.gen_rust_encoded(None, Encoding::default()) }
+
+  // `self` should be built from `tokens`, in addition to `gen_rust()`, the module contains a constant for each token id,
+  // which has the same name as the token, and `NAMES` and `SKIP` for the name and skip flag of each token
+  // so token names shouldn't conflict with other items in the module, like `EC` or `next_token`
+  pub fn gen_rust_with<'a>(&'a self, tokens: &'a TokenSet) -> impl Display + 'a { self.gen_rust_encoded(Some(tokens), Encoding::default()) }
+
+  // the general form of `gen_rust()` and `gen_rust_with()`, with `Encoding::Comb` the transition table is replaced by
+  // `BASE`, `DEFAULT`, `NEXT` and `CHECK` arrays, and a comment reports the compression ratio
+  // with `Encoding::Direct` there is no `EC`, `DEAD` or transition table, and `next_token` matches on the byte ranges of each state
+  pub fn gen_rust_encoded<'a>(&'a self, tokens: Option<&'a TokenSet>, enc: Encoding) -> impl Display + 'a {
+    fn2display(move |f| {
+      let (n, ec_num) = (self.nodes.len(), self.ec_num);
+      let ty = uint_ty(n);
+      writeln!(f, "// generated by re2dfa, do not edit\n")?;
+      if enc != Encoding::Direct { writeln!(f, "pub const EC_NUM: usize = {};", ec_num)?; }
+      writeln!(f, "pub const STATE_NUM: usize = {};", n)?;
+      if enc != Encoding::Direct { writeln!(f, "pub const DEAD: {} = {};", ty, n)?; }
+      f.write_str("\n")?;
+      if let Some(tokens) = tokens {
+        writeln!(f, "pub const TOKEN_NUM: usize = {};", tokens.tokens.len())?;
+        for (id, t) in tokens.tokens.iter().enumerate() { writeln!(f, "pub const {}: u32 = {};", t.name, id)?; }
+        f.write_str("pub static NAMES: [&str; TOKEN_NUM] = [")?;
+        write_list(f, tokens.tokens.iter().map(|t| fn2display(move |f| write!(f, "{:?}", t.name))), usize::MAX)?;
+        f.write_str("];\npub static SKIP: [bool; TOKEN_NUM] = [")?;
+        write_list(f, tokens.tokens.iter().map(|t| t.skip), usize::MAX)?;
+        f.write_str("];\n\n")?;
+      }
+      if enc != Encoding::Direct {
+        f.write_str("pub static EC: [u8; 256] = [\n  ")?;
+        write_list(f, self.ec.iter(), 16)?;
+        f.write_str("\n];\n\n")?;
+      }
+      match enc {
+        Encoding::Dense => {
+          writeln!(f, "pub static TABLE: [[{}; EC_NUM]; STATE_NUM] = [", ty)?;
+          for (_, edges) in &self.nodes {
+            f.write_str("  [")?;
+            write_list(f, (0..ec_num).map(|k| edges.get(&(k as u8)).map_or(n, |&out| out as usize)), usize::MAX)?;
+            f.write_str("],\n")?;
+          }
+          f.write_str("];\n\n")?;
+        }
+        Encoding::Comb => {
+          let comb = CombDfa::from_dfa(self);
+          // `DEAD` in `CombDfa` is replaced by state `n`, which also marks the unused slots in `CHECK`
+          let state = |x: &u32| if *x == DEAD { n } else { *x as usize };
+          writeln!(f, "// comb table: {} entries, {:.1}% of the dense table", comb.size(), comb.ratio() * 100.0)?;
+          writeln!(f, "pub const COMB_LEN: usize = {} + EC_NUM;\n", comb.next.len() - ec_num)?;
+          write!(f, "pub static BASE: [{}; STATE_NUM] = [\n  ", uint_ty(comb.next.len()))?;
+          write_list(f, comb.base.iter(), 16)?;
+          write!(f, "\n];\n\npub static DEFAULT: [{}; STATE_NUM] = [\n  ", ty)?;
+          write_list(f, comb.default.iter().map(state), 16)?;
+          write!(f, "\n];\n\npub static NEXT: [{}; COMB_LEN] = [\n  ", ty)?;
+          write_list(f, comb.next.iter().map(state), 16)?;
+          write!(f, "\n];\n\npub static CHECK: [{}; COMB_LEN] = [\n  ", ty)?;
+          write_list(f, comb.check.iter().map(state), 16)?;
+          f.write_str("\n];\n\n")?;
+          write!(f, r#"// return the next state of `state` after reading a char in class `k`, or `DEAD`
+fn next_state(mut state: usize, k: usize) -> {} {{
+  loop {{
+    let i = BASE[state] as usize + k;
+    if CHECK[i] as usize == state {{ return NEXT[i]; }}
+    if DEFAULT[state] == DEAD {{ return DEAD; }}
+    state = DEFAULT[state] as usize;
+  }}
+}}
+
+"#, ty)?;
+        }
+        Encoding::Direct => {}
+      }
+      f.write_str("pub static ACC: [Option<u32>; STATE_NUM] = [\n  ")?;
+      write_list(f, self.nodes.iter().map(|&(acc, _)| fn2display(move |f| match acc {
+        Some(id) => write!(f, "Some({})", id),
+        None => f.write_str("None"),
+      })), 8)?;
+      f.write_str("\n];\n\n")?;
+      let next = match enc {
+        Encoding::Dense => "TABLE[state][EC[ch as usize] as usize]",
+        Encoding::Comb => "next_state(state, EC[ch as usize] as usize)",
+        Encoding::Direct => return self.gen_rust_direct(f),
+      };
+      write!(f, r#"// return (id, len): the longest non-empty prefix of `input` is `input[..len]`, and it is accepted by pattern `id`
+pub fn next_token(input: &[u8]) -> Option<(u32, usize)> {{
+  let (mut state, mut last) = (0, None);
+  for (i, &ch) in input.iter().enumerate() {{
+    let next = {};
+    if next == DEAD {{ break; }}
+    state = next as usize;
+    if let Some(id) = ACC[state] {{ last = Some((id, i + 1)); }}
+  }}
+  last
+}}
+"#, next)
+    })
+  }
+
+  // the `next_token` function of `Encoding::Direct`
+  fn gen_rust_direct(&self, f: &mut Formatter) -> fmt::Result {
+    f.write_str(r#"// return (id, len): the longest non-empty prefix of `input` is `input[..len]`, and it is accepted by pattern `id`
+pub fn next_token(input: &[u8]) -> Option<(u32, usize)> {
+  let (mut state, mut last) = (0, None);
+  for (i, &ch) in input.iter().enumerate() {
+    state = match state {
+"#)?;
+    for (idx, (_, edges)) in self.nodes.iter().enumerate() {
+      // the byte ranges going to each state, in the order of their first bytes
+      let mut ranges = Vec::<(u32, Vec<(u8, u8)>)>::new();
+      let mut pos = HashMap::default();
+      for ch in 0..=255u8 {
+        let out = match edges.get(&self.ec[ch as usize]) { Some(&out) => out, None => continue };
+        let i = *pos.entry(out).or_insert_with(|| {
+          ranges.push((out, vec![]));
+          ranges.len() - 1
+        });
+        let rs = &mut ranges[i].1;
+        match rs.last_mut() {
+          Some((_, hi)) if *hi as usize + 1 == ch as usize => *hi = ch,
+          _ => rs.push((ch, ch)),
+        }
+      }
+      if ranges.is_empty() {
+        writeln!(f, "      {} => break,", idx)?;
+        continue;
+      }
+      writeln!(f, "      {} => match ch {{", idx)?;
+      for (out, rs) in &ranges {
+        f.write_str("        ")?;
+        for (i, &(lo, hi)) in rs.iter().enumerate() {
+          if i != 0 { f.write_str(" | ")?; }
+          if lo == hi { write!(f, "{}", byte_pat(lo))?; } else { write!(f, "{}..={}", byte_pat(lo), byte_pat(hi))?; }
+        }
+        writeln!(f, " => {},", out)?;
+      }
+      // if all bytes have edges, a `_` arm would be unreachable
+      if ranges.iter().map(|(_, rs)| rs.iter().map(|&(lo, hi)| hi as usize - lo as usize + 1).sum::<usize>()).sum::<usize>() != 256 {
+        f.write_str("        _ => break,\n")?;
+      }
+      f.write_str("      },\n")?;
+    }
+    f.write_str(r#"      _ => unreachable!(),
+    };
+    if let Some(id) = ACC[state] { last = Some((id, i + 1)); }
+  }
+  last
+}
+"#)
+  }
+}
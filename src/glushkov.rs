@@ -0,0 +1,116 @@
+use smallvec::{SmallVec, smallvec};
+use crate::*;
+
+// the part of nfa generated from a regex: the edges into it from outside, the nodes it can end at,
+// and whether it accepts the empty string
+struct Frag {
+  first: Vec<(u8, u32)>,
+  last: Vec<u32>,
+  nullable: bool,
+}
+
+impl Nfa {
+  // Glushkov construction (position automaton), each char set in `re` becomes a node, and there is no eps edge,
+  // so `e_close` is left empty, the number of edges can be quadratic in the size of `re`
+  // `~` and `&` are compiled into minimized dfas, whose states become nodes
+  // if multiple patterns accept the empty string, start state only accepts the first of them
+  pub fn glushkov(re: &[Re]) -> Nfa {
+    match Nfa::glushkov_limited(re, &Limits::default()) {
+      Ok(nfa) => nfa,
+      Err(_) => unreachable!(),
+    }
+  }
+
+  // the number of nodes and the dfas compiled for `~` and `&` are checked during construction, see `Nfa::thompson_limited`
+  pub fn glushkov_limited(re: &[Re], limits: &Limits) -> Result<Nfa, LimitError> {
+    let (ec_num, ec) = nfa::compute_ec(re);
+    let mut nfa = Nfa { nodes: vec![NfaNode::new(None, SmallVec::new(), HashMap::default())], e_close: [].into(), ec_num, ec };
+    let mut starts = Vec::with_capacity(re.len());
+    for (id, re) in re.iter().enumerate() {
+      starts.push(nfa.nodes.len());
+      let f = nfa.glushkov1(re, limits).map_err(|e| nfa.construction_error(e, limits, &starts))?;
+      nfa.link(&[0], &f.first);
+      for &l in &f.last { nfa.nodes[l as usize].id = Some(id as u32); }
+      if f.nullable && nfa.nodes[0].id.is_none() { nfa.nodes[0].id = Some(id as u32); }
+    }
+    Ok(nfa)
+  }
+
+  fn glushkov1(&mut self, re: &Re, limits: &Limits) -> Result<Frag, LimitError> {
+    let f = match re {
+      Eps => Frag { first: Vec::new(), last: Vec::new(), nullable: true },
+      &Ch(ch) => {
+        let mut set = [0; 8];
+        bitset::bs(&mut set).set(ch as usize);
+        self.position(&set)
+      }
+      DisjunctionCh(set) => self.position(set),
+      Concat(x) => {
+        let mut ret = Frag { first: Vec::new(), last: Vec::new(), nullable: true };
+        for x in x.iter() {
+          let f = self.glushkov1(x, limits)?;
+          self.link(&ret.last, &f.first);
+          if ret.nullable { ret.first.extend_from_slice(&f.first); }
+          if f.nullable { ret.last.extend(f.last); } else { ret.last = f.last; }
+          ret.nullable &= f.nullable;
+        }
+        ret
+      }
+      Disjunction(x) => {
+        let mut ret = Frag { first: Vec::new(), last: Vec::new(), nullable: false };
+        for x in x.iter() {
+          let f = self.glushkov1(x, limits)?;
+          ret.first.extend(f.first);
+          ret.last.extend(f.last);
+          ret.nullable |= f.nullable;
+        }
+        ret
+      }
+      Kleene(x) => {
+        let f = self.glushkov1(x, limits)?;
+        self.link(&f.last, &f.first);
+        Frag { nullable: true, ..f }
+      }
+      Complement(x) => self.dfa_frag(self.compile_dfa(std::slice::from_ref(&**x), |acc| !acc[0], limits)?),
+      Intersection(x) => self.dfa_frag(self.compile_dfa(x, |acc| acc.iter().all(|&a| a), limits)?),
+    };
+    self.check_nodes(limits)?;
+    Ok(f)
+  }
+
+  // add edges from each of `from` to each of `to`
+  fn link(&mut self, from: &[u32], to: &[(u8, u32)]) {
+    for &x in from {
+      let edges = &mut self.nodes[x as usize].edges;
+      for &(k, y) in to {
+        let outs = edges.entry(k).or_default();
+        if !outs.contains(&y) { outs.push(y); }
+      }
+    }
+  }
+
+  // a new node that is entered by reading a char in `set`
+  fn position(&mut self, set: &[u32; 8]) -> Frag {
+    let p = self.nodes.len() as u32;
+    self.nodes.push(NfaNode::new(None, SmallVec::new(), HashMap::default()));
+    let mut first = Vec::new();
+    bitset::ibs(set).ones(|ch| first.push((self.ec[ch], p)));
+    first.sort_unstable();
+    first.dedup();
+    Frag { first, last: vec![p], nullable: false }
+  }
+
+  // each state of `dfa` becomes a node, the edges out of its start state are the edges into the fragment
+  fn dfa_frag(&mut self, dfa: Dfa) -> Frag {
+    let base = self.nodes.len() as u32;
+    let mut last = Vec::new();
+    for (i, (acc, edges)) in dfa.nodes.iter().enumerate() {
+      if acc.is_some() { last.push(base + i as u32); }
+      let edges = edges.iter().map(|(&k, &out)| (k, smallvec![base + out])).collect();
+      self.nodes.push(NfaNode::new(None, SmallVec::new(), edges));
+    }
+    let mut first = dfa.nodes[0].1.iter().map(|(&k, &out)| (k, base + out)).collect::<Vec<_>>();
+    first.sort_unstable();
+    Frag { first, last, nullable: dfa.nodes[0].0.is_some() }
+  }
+}
@@ -0,0 +1,108 @@
+use std::cell::RefCell;
+use crate::*;
+
+// a dfa whose states are built from `nfa` by subset construction on demand during matching, like `Dfa::from_nfa`
+// at most `cache_size` states are kept, when there is no room for a new state, all states except start are dropped
+// it gives the same results as the dfa from `Dfa::from_nfa(nfa)`, and it can be used where the full dfa is too large
+// state ids are only valid until the next flush, but `Automaton::longest_match` and `Lexer` only use the latest state
+pub struct LazyDfa<'a> {
+  pub nfa: &'a Nfa,
+  pub cache_size: usize,
+  cache: RefCell<Cache>,
+}
+
+// the transition of a state via an ec that has not been computed yet
+const UNKNOWN: u32 = DEAD - 1;
+
+struct Cache {
+  // `sets[i]` is the nfa state set of state i, and `ids` is its inverse
+  sets: Vec<Box<[u32]>>,
+  ids: HashMap<Box<[u32]>, u32>,
+  acc: Vec<Option<u32>>,
+  // `trans[i * ec_num + k]` is the next state of state i via ec k, `DEAD` or `UNKNOWN`
+  trans: Vec<u32>,
+  flushes: usize,
+  tmp: Box<[u32]>,
+  stack: Vec<u32>,
+}
+
+impl<'a> LazyDfa<'a> {
+  // `cache_size` should be at least 2, so that there is room for start state and the current state
+  pub fn new(nfa: &'a Nfa, cache_size: usize) -> LazyDfa<'a> {
+    assert!(cache_size >= 2);
+    let elem_len = bitset::bslen(nfa.nodes.len());
+    let mut cache = Cache {
+      sets: Vec::new(),
+      ids: HashMap::default(),
+      acc: Vec::new(),
+      trans: Vec::new(),
+      flushes: 0,
+      tmp: vec![0; elem_len].into(),
+      stack: Vec::new(),
+    };
+    let start = nfa.start_set(&mut cache.stack);
+    cache.add(nfa, start);
+    LazyDfa { nfa, cache_size, cache: RefCell::new(cache) }
+  }
+
+  // the number of states in the cache
+  pub fn cached(&self) -> usize { self.cache.borrow().sets.len() }
+
+  // the number of times the cache is flushed
+  pub fn flushes(&self) -> usize { self.cache.borrow().flushes }
+}
+
+impl Cache {
+  fn add(&mut self, nfa: &Nfa, set: Box<[u32]>) -> u32 {
+    let id = self.sets.len() as u32;
+    self.acc.push(nfa.set_acc(&set));
+    self.trans.resize(self.trans.len() + nfa.ec_num, UNKNOWN);
+    self.ids.insert(set.clone(), id);
+    self.sets.push(set);
+    id
+  }
+
+  // drop all states except start state, which is still 0
+  fn flush(&mut self, nfa: &Nfa) {
+    let start = self.sets.swap_remove(0);
+    self.sets.clear();
+    self.ids.clear();
+    self.acc.clear();
+    self.trans.clear();
+    self.flushes += 1;
+    self.add(nfa, start);
+  }
+}
+
+impl Automaton for LazyDfa<'_> {
+  fn next(&self, state: u32, ch: u8) -> Option<u32> {
+    let (nfa, ec_num) = (self.nfa, self.nfa.ec_num);
+    let k = nfa.ec[ch as usize];
+    let c = &mut *self.cache.borrow_mut();
+    let idx = state as usize * ec_num + k as usize;
+    match c.trans[idx] {
+      UNKNOWN => {}
+      DEAD => return None,
+      next => return Some(next),
+    }
+    nfa.step(&c.sets[state as usize], k, &mut c.tmp, &mut c.stack);
+    let next = if c.tmp.iter().all(|&x| x == 0) {
+      DEAD
+    } else if let Some(&id) = c.ids.get(&c.tmp) {
+      id
+    } else {
+      if c.sets.len() >= self.cache_size {
+        // `state` is dropped, so the transition can't be recorded
+        c.flush(nfa);
+        let set = c.tmp.clone();
+        return Some(c.add(nfa, set));
+      }
+      let set = c.tmp.clone();
+      c.add(nfa, set)
+    };
+    c.trans[idx] = next;
+    if next == DEAD { None } else { Some(next) }
+  }
+
+  fn acc(&self, state: u32) -> Option<u32> { self.cache.borrow().acc[state as usize] }
+}
@@ -0,0 +1,76 @@
+use std::ops::Range;
+use crate::*;
+
+// the common interface of the automata that can be used to match input, the start state is always 0
+pub trait Automaton {
+  // return the next state of `state` after reading `ch`, or None if it goes to the dead state
+  fn next(&self, state: u32, ch: u8) -> Option<u32>;
+
+  // return the id of the pattern that `state` accepts
+  fn acc(&self, state: u32) -> Option<u32>;
+
+  // return (id, len): the longest non-empty prefix of `input` is `input[..len]`, and it is accepted by pattern `id`
+  // if multiple patterns accept it, the dfa has already chosen the first one among them
+  fn longest_match(&self, input: &[u8]) -> Option<(u32, usize)> {
+    let (mut state, mut last) = (0, None);
+    for (i, &ch) in input.iter().enumerate() {
+      match self.next(state, ch) {
+        Some(next) => state = next,
+        None => break,
+      }
+      if let Some(id) = self.acc(state) { last = Some((id, i + 1)); }
+    }
+    last
+  }
+
+  fn tokens<'a>(&'a self, input: &'a [u8]) -> Lexer<'a, Self> where Self: Sized { Lexer::new(self, input) }
+}
+
+impl Automaton for Dfa {
+  fn next(&self, state: u32, ch: u8) -> Option<u32> {
+    self.nodes[state as usize].1.get(&self.ec[ch as usize]).copied()
+  }
+
+  fn acc(&self, state: u32) -> Option<u32> { self.nodes[state as usize].0 }
+}
+
+impl Automaton for DenseDfa {
+  fn next(&self, state: u32, ch: u8) -> Option<u32> {
+    match DenseDfa::next(self, state, ch) { DEAD => None, next => Some(next) }
+  }
+
+  fn acc(&self, state: u32) -> Option<u32> { self.acc[state as usize] }
+}
+
+// split `input` into tokens by repeatedly applying `longest_match` at `pos`
+// yield Ok((id, range)) for a token accepted by pattern `id`, whose content is `input[range]`
+// yield Err(pos) if no pattern accepts a non-empty prefix of `input[pos..]`, then skip one byte and continue
+// empty match is not regarded as a token, otherwise the lexer can't make progress
+pub struct Lexer<'a, A: ?Sized = Dfa> {
+  pub automaton: &'a A,
+  pub input: &'a [u8],
+  pub pos: usize,
+}
+
+impl<'a, A: Automaton + ?Sized> Lexer<'a, A> {
+  pub fn new(automaton: &'a A, input: &'a [u8]) -> Lexer<'a, A> { Lexer { automaton, input, pos: 0 } }
+}
+
+impl<A: Automaton + ?Sized> Iterator for Lexer<'_, A> {
+  type Item = Result<(u32, Range<usize>), usize>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let start = self.pos;
+    if start == self.input.len() { return None; }
+    Some(match self.automaton.longest_match(&self.input[start..]) {
+      Some((id, len)) => {
+        self.pos += len;
+        Ok((id, start..self.pos))
+      }
+      None => {
+        self.pos += 1;
+        Err(start)
+      }
+    })
+  }
+}
@@ -1,19 +1,121 @@
 pub mod re;
 pub mod nfa;
+pub mod glushkov;
 pub mod dfa;
+pub mod derivative;
+pub mod dense;
+pub mod comb;
+pub mod binary;
+pub mod lexer;
+pub mod lazy;
+pub mod token;
+pub mod spec;
 pub mod print;
+pub mod analysis;
+pub mod limit;
+pub mod product;
+pub mod gen_rust;
+pub mod gen_c;
+#[cfg(feature = "serde")]
+mod serde_impl;
 
 pub use re::{*, Re::*};
 pub use nfa::*;
 pub use dfa::*;
+pub use dense::*;
+pub use comb::*;
+pub use binary::*;
+pub use lexer::*;
+pub use lazy::*;
+pub use token::*;
+pub use spec::*;
 pub use print::*;
+pub use analysis::*;
+pub use limit::*;
+pub use product::*;
 
 use tools::{*, fmt::*};
 
-// return Err((idx, reason)): `re[idx]` is invalid because of the syntax error described in`reason`
-pub fn re2dfa<'a>(re: impl IntoIterator<Item=&'a [u8]>) -> Result<Dfa, (usize, String)> {
-  let nfa = Nfa::from_re(re)?;
-  let mut dfa = Dfa::from_nfa(&nfa);
+// the algorithm used to build a dfa from regexes, all of them give equivalent minimized dfas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Construction {
+  // Thompson nfa, then subset construction
+  #[default]
+  Thompson,
+  // Glushkov nfa without eps edges, see `Nfa::glushkov`, then subset construction
+  Glushkov,
+  // Brzozowski derivatives, see `Dfa::from_re`
+  Derivative,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+  pub construction: Construction,
+  // for `Construction::Thompson`, compute eps closures on demand in subset construction, instead of computing
+  // the `n * n` closure matrix in advance, which is too large for a large nfa
+  pub lazy_e_close: bool,
+  pub limits: Limits,
+}
+
+// how the transitions are stored in the code generated by `Dfa::gen_rust_encoded` and `Dfa::gen_c`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+  // a `STATE_NUM * EC_NUM` table, see `DenseDfa`
+  Dense,
+  // base/default/next/check arrays, see `CombDfa`, which is the default because it is usually much smaller
+  #[default]
+  Comb,
+  // no transition table, each state is a branch of a `match` (or `switch` in C) in `next_token`, like re2c,
+  // which is faster for small lexers, but the code size grows with the number of edges
+  Direct,
+}
+
+// return Err(e): `re[e.idx]` is invalid because of the syntax error `e.kind` at `re[e.idx][e.offset]`
+pub fn re2dfa<'a>(re: impl IntoIterator<Item=&'a [u8]>) -> Result<Dfa, ParseError> {
+  match re2dfa_with(re, &Options::default()) {
+    Ok(dfa) => Ok(dfa),
+    Err(BuildError::Parse(e)) => Err(e),
+    // there is no limit by default
+    Err(BuildError::Limit(_)) => unreachable!(),
+  }
+}
+
+// return Err(BuildError::Limit(e)) if construction exceeds `opt.limits`, including the dfas compiled for `~` and `&`
+pub fn re2dfa_with<'a>(re: impl IntoIterator<Item=&'a [u8]>, opt: &Options) -> Result<Dfa, BuildError> {
+  let re = parse_all(re)?;
+  let mut dfa = build(&re, opt).map_err(|e| {
+    // a pattern that exceeds the limits on its own is surely a cause, so check the candidates one by one
+    let alone = e.patterns.iter().copied().filter(|&i| build(std::slice::from_ref(&re[i]), opt).is_err()).collect::<Vec<_>>();
+    if alone.is_empty() { e } else { LimitError { patterns: alone, ..e } }
+  })?;
   dfa.minimize();
   Ok(dfa)
+}
+
+fn build(re: &[Re], opt: &Options) -> Result<Dfa, LimitError> {
+  Ok(match opt.construction {
+    Construction::Thompson => {
+      let mut nfa = Nfa::thompson_limited(re, &opt.limits)?;
+      nfa.check_limits(&opt.limits)?;
+      // the closure matrix is not computed if it exceeds the memory limit, then closures are computed lazily
+      let n = nfa.nodes.len();
+      if !opt.lazy_e_close && n * bitset::bslen(n) * 4 + nfa.memory() <= opt.limits.memory { nfa.compute_e_close(); }
+      Dfa::from_nfa_limited(&nfa, &opt.limits)?.0
+    }
+    Construction::Glushkov => {
+      let nfa = Nfa::glushkov_limited(re, &opt.limits)?;
+      nfa.check_limits(&opt.limits)?;
+      Dfa::from_nfa_limited(&nfa, &opt.limits)?.0
+    }
+    Construction::Derivative => Dfa::from_re_limited(re, &opt.limits)?,
+  })
+}
+
+// like `re2dfa`, but also return the patterns that can never be the result of matching
+pub fn re2dfa_with_warnings<'a>(re: impl IntoIterator<Item=&'a [u8]>) -> Result<(Dfa, Vec<Shadowed>), ParseError> {
+  let nfa = Nfa::from_re(re)?;
+  let (mut dfa, sets) = Dfa::from_nfa_with_sets(&nfa);
+  let warnings = analysis::shadowed(&nfa, &dfa, &sets, nfa.pattern_num(), |id| id);
+  dfa.minimize();
+  Ok((dfa, warnings))
 }
\ No newline at end of file
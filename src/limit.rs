@@ -0,0 +1,155 @@
+use smallvec::SmallVec;
+use std::fmt;
+use crate::*;
+
+// the limits on the size of automata during construction, the default has no limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+  pub nfa_nodes: usize,
+  pub dfa_states: usize,
+  // in bytes, it is an estimation of the memory used by the nfa and the subset construction, or the derivatives
+  pub memory: usize,
+}
+
+impl Default for Limits {
+  fn default() -> Limits { Limits { nfa_nodes: usize::MAX, dfa_states: usize::MAX, memory: usize::MAX } }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitKind { NfaNodes, DfaStates, Memory }
+
+// construction stopped because the limit of `kind` was exceeded, `patterns` are the indices of the patterns that
+// most likely caused it, see `culprits`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitError {
+  pub kind: LimitKind,
+  pub patterns: Vec<usize>,
+}
+
+impl fmt::Display for LimitError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(match self.kind {
+      LimitKind::NfaNodes => "too many nfa nodes",
+      LimitKind::DfaStates => "too many dfa states",
+      LimitKind::Memory => "too much memory",
+    })?;
+    for (i, p) in self.patterns.iter().enumerate() {
+      write!(f, "{}{}", if i == 0 { ", caused by pattern " } else { ", " }, p)?;
+    }
+    Ok(())
+  }
+}
+
+impl std::error::Error for LimitError {}
+
+// the error of `re2dfa_with`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+  Parse(ParseError),
+  Limit(LimitError),
+}
+
+impl From<ParseError> for BuildError {
+  fn from(e: ParseError) -> Self { BuildError::Parse(e) }
+}
+
+impl From<LimitError> for BuildError {
+  fn from(e: LimitError) -> Self { BuildError::Limit(e) }
+}
+
+impl fmt::Display for BuildError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self { BuildError::Parse(e) => e.fmt(f), BuildError::Limit(e) => e.fmt(f) }
+  }
+}
+
+impl std::error::Error for BuildError {}
+
+// `involved[i]` is the number of states (or nodes) involving pattern i, a blow-up is usually caused by a few patterns
+// involved in most states, so the patterns involved in at least half as many states as the most involved one are reported
+pub(crate) fn culprits(involved: &[usize]) -> Vec<usize> {
+  let max = involved.iter().copied().max().unwrap_or(0);
+  (0..involved.len()).filter(|&i| involved[i] != 0 && involved[i] * 2 >= max).collect()
+}
+
+impl Nfa {
+  // the pattern each node belongs to, i.e., the accept id of the accepting nodes reachable from it
+  // start state is shared by all patterns, so it belongs to none of them
+  pub(crate) fn owners(&self) -> Vec<Option<u32>> {
+    let n = self.nodes.len();
+    let mut rev = vec![Vec::new(); n];
+    for (i, node) in self.nodes.iter().enumerate() {
+      for &out in node.eps_edges.iter().chain(node.edges.values().flatten()) { rev[out as usize].push(i as u32); }
+    }
+    let mut owner = vec![None; n];
+    let mut stack = Vec::new();
+    for (i, node) in self.nodes.iter().enumerate().skip(1) {
+      if node.id.is_some() {
+        owner[i] = node.id;
+        stack.push(i as u32);
+      }
+    }
+    while let Some(x) = stack.pop() {
+      for &y in &rev[x as usize] {
+        if y != 0 && owner[y as usize].is_none() {
+          owner[y as usize] = owner[x as usize];
+          stack.push(y);
+        }
+      }
+    }
+    owner
+  }
+
+  // the estimated memory used by the nodes and edges, not including `e_close`
+  pub(crate) fn memory(&self) -> usize {
+    let edge = std::mem::size_of::<(u8, SmallVec<[u32; 4]>)>();
+    self.nodes.iter().map(|n| {
+      let spilled = n.edges.values().chain(Some(&n.eps_edges)).filter(|outs| outs.spilled()).map(|outs| outs.capacity() * 4).sum::<usize>();
+      std::mem::size_of::<NfaNode>() + n.edges.len() * edge + spilled
+    }).sum()
+  }
+
+  // checked whenever nodes are added during construction, so that a huge nfa is never built
+  // `patterns` is left for `construction_error` to fill
+  pub(crate) fn check_nodes(&self, limits: &Limits) -> Result<(), LimitError> {
+    if self.nodes.len() > limits.nfa_nodes { Err(LimitError { kind: LimitKind::NfaNodes, patterns: Vec::new() }) } else { Ok(()) }
+  }
+
+  // `e` stopped the construction of the last pattern, and the nodes of pattern i start at `starts[i]`
+  // if this nfa has too many nodes, the patterns with the most nodes so far are blamed,
+  // otherwise `e` comes from a dfa compiled for `~` or `&` in the last pattern
+  pub(crate) fn construction_error(&self, e: LimitError, limits: &Limits, starts: &[usize]) -> LimitError {
+    let patterns = if self.nodes.len() > limits.nfa_nodes {
+      let end = |i: usize| starts.get(i + 1).copied().unwrap_or(self.nodes.len());
+      culprits(&(0..starts.len()).map(|i| end(i) - starts[i]).collect::<Vec<_>>())
+    } else { vec![starts.len() - 1] };
+    LimitError { patterns, ..e }
+  }
+
+  pub(crate) fn check_limits(&self, limits: &Limits) -> Result<(), LimitError> {
+    let kind = if self.nodes.len() > limits.nfa_nodes {
+      LimitKind::NfaNodes
+    } else if self.memory() > limits.memory {
+      LimitKind::Memory
+    } else { return Ok(()); };
+    let mut involved = vec![0; self.pattern_num()];
+    for o in self.owners().into_iter().flatten() { involved[o as usize] += 1; }
+    Err(LimitError { kind, patterns: culprits(&involved) })
+  }
+
+  // `sets` are the nfa state sets of the dfa states found before the subset construction stopped
+  pub(crate) fn subset_limit_error(&self, kind: LimitKind, sets: &[Box<[u32]>]) -> LimitError {
+    let owner = self.owners();
+    let (mut involved, mut seen) = (vec![0; self.pattern_num()], vec![usize::MAX; self.pattern_num()]);
+    for (s, set) in sets.iter().enumerate() {
+      bitset::ibs(set).ones(|i| if let Some(p) = owner[i] {
+        let p = p as usize;
+        if seen[p] != s {
+          seen[p] = s;
+          involved[p] += 1;
+        }
+      });
+    }
+    LimitError { kind, patterns: culprits(&involved) }
+  }
+}
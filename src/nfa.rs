@@ -10,93 +10,117 @@ pub struct NfaNode {
 }
 
 impl NfaNode {
-  fn new(id: Option<u32>, eps_edges: SmallVec<[u32; 4]>, edges: HashMap<u8, SmallVec<[u32; 4]>>) -> NfaNode {
+  pub(crate) fn new(id: Option<u32>, eps_edges: SmallVec<[u32; 4]>, edges: HashMap<u8, SmallVec<[u32; 4]>>) -> NfaNode {
     NfaNode { id, eps_edges, edges }
   }
 }
 
+// the equivalence classes of chars: 2 chars are in the same class iff no part of `re` distinguishes them
+pub(crate) fn compute_ec(re: &[Re]) -> (usize, [u8; 256]) {
+  let mut edges = [[0u32; 8]; 256];
+  for re in re {
+    unsafe fn dfs(edges: &mut [[u32; 8]; 256], re: &Re) {
+      match re {
+        Eps => {}
+        &Ch(x) => {
+          let x = x as usize;
+          for i in 0..x { bitset::ubs(edges.get_unchecked(i)).set(x); }
+          for i in x..256 { bitset::ubs(edges.get_unchecked(x)).set(i); }
+        }
+        Concat(x) | Disjunction(x) => for x in x.iter() { dfs(edges, x); }
+        DisjunctionCh(x) => {
+          let ubs = bitset::ubs(x.as_ref());
+          bitset::ibs(x.as_ref()).ones(|x| {
+            for i in 0..x { if !ubs.get(i) { bitset::ubs(edges.get_unchecked(i)).set(x); } }
+            for i in x..256 { if !ubs.get(i) { bitset::ubs(edges.get_unchecked(x)).set(i); } }
+          })
+        }
+        Kleene(x) | Complement(x) => dfs(edges, x),
+        Intersection(x) => for x in x.iter() { dfs(edges, x); }
+      }
+    }
+    unsafe { dfs(&mut edges, re); }
+  }
+  let mut vis = [false; 256];
+  unsafe fn dfs(edges: &[[u32; 8]; 256], ec: &mut [u8; 256], vis: &mut [bool; 256], ch: usize, id: usize) {
+    let v = vis.get_unchecked_mut(ch);
+    if *v { return; }
+    *v = true;
+    *ec.get_unchecked_mut(ch) = id as u8;
+    for i in ch..256 {
+      if !bitset::ubs(edges.get_unchecked(ch)).get(i as usize) {
+        dfs(edges, ec, vis, i, id);
+      }
+    }
+  }
+
+  let mut ec_num = 0;
+  let mut ec = [0; 256];
+  for ch in 0..256 {
+    if !vis[ch as usize] {
+      unsafe { dfs(&edges, &mut ec, &mut vis, ch, ec_num); }
+      ec_num += 1;
+    }
+  }
+  (ec_num, ec)
+}
+
 // start state should be 0, a valid Nfa should have nodes.len() >= 1
 pub struct Nfa {
   pub nodes: Vec<NfaNode>,
+  // the eps closure of node i is the bitset `e_close[i * bslen(nodes.len())..(i + 1) * bslen(nodes.len())]`
+  // it can be empty, then the eps closures are computed on demand, see `add_e_close`
   pub e_close: Box<[u32]>,
   pub ec_num: usize,
   pub ec: [u8; 256],
 }
 
 impl Nfa {
-  pub fn from_re<'a>(re: impl IntoIterator<Item=&'a [u8]>) -> Result<Nfa, (usize, String)> {
-    let mut buf = Vec::new();
-    for (id, re) in re.into_iter().enumerate() {
-      match parse(re) {
-        Ok(re) => buf.push(re),
-        Err(err) => return Err((id, err)),
-      }
-    }
-    Ok(Nfa::from_re1(&buf))
+  pub fn from_re<'a>(re: impl IntoIterator<Item=&'a [u8]>) -> Result<Nfa, ParseError> {
+    Ok(Nfa::from_re1(&parse_all(re)?))
   }
 
   pub fn from_re1(re: &[Re]) -> Nfa {
-    let mut edges = [[0u32; 8]; 256];
-    for re in re {
-      unsafe fn dfs(edges: &mut [[u32; 8]; 256], re: &Re) {
-        match re {
-          Eps => {}
-          &Ch(x) => {
-            let x = x as usize;
-            for i in 0..x { bitset::ubs(edges.get_unchecked(i)).set(x); }
-            for i in x..256 { bitset::ubs(edges.get_unchecked(x)).set(i); }
-          }
-          Concat(x) | Disjunction(x) => for x in x.iter() { dfs(edges, x); }
-          DisjunctionCh(x) => {
-            let ubs = bitset::ubs(x.as_ref());
-            bitset::ibs(x.as_ref()).ones(|x| {
-              for i in 0..x { if !ubs.get(i) { bitset::ubs(edges.get_unchecked(i)).set(x); } }
-              for i in x..256 { if !ubs.get(i) { bitset::ubs(edges.get_unchecked(x)).set(i); } }
-            })
-          }
-          Kleene(x) => dfs(edges, x),
-        }
-      }
-      unsafe { dfs(&mut edges, re); }
+    let mut nfa = Nfa::thompson(re);
+    nfa.compute_e_close();
+    nfa
+  }
+
+  // the same as `from_re1`, except that `e_close` is left empty, so it can be computed lazily by `Dfa::from_nfa`
+  pub fn thompson(re: &[Re]) -> Nfa {
+    match Nfa::thompson_limited(re, &Limits::default()) {
+      Ok(nfa) => nfa,
+      Err(_) => unreachable!(),
     }
-    let (ec_num, ec) = {
-      let mut vis = [false; 256];
-      unsafe fn dfs(edges: &[[u32; 8]; 256], ec: &mut [u8; 256], vis: &mut [bool; 256], ch: usize, id: usize) {
-        let v = vis.get_unchecked_mut(ch);
-        if *v { return; }
-        *v = true;
-        *ec.get_unchecked_mut(ch) = id as u8;
-        for i in ch..256 {
-          if !bitset::ubs(edges.get_unchecked(ch)).get(i as usize) {
-            dfs(edges, ec, vis, i, id);
-          }
-        }
-      }
+  }
 
-      let mut ec_num = 0;
-      let mut ec = [0; 256];
-      for ch in 0..256 {
-        if !vis[ch as usize] {
-          unsafe { dfs(&edges, &mut ec, &mut vis, ch, ec_num); }
-          ec_num += 1;
-        }
-      }
-      (ec_num, ec)
-    };
+  // the number of nodes and the dfas compiled for `~` and `&` are checked against `limits` during construction,
+  // so it stops as soon as a limit is exceeded, the memory limit on the returned nfa is not checked
+  pub fn thompson_limited(re: &[Re], limits: &Limits) -> Result<Nfa, LimitError> {
+    let (ec_num, ec) = compute_ec(re);
+    Nfa::from_re_with_ec(re, ec_num, ec, limits)
+  }
 
+  // `ec` should be fine enough for `re`, i.e., it never maps 2 chars distinguished by `re` to the same class
+  fn from_re_with_ec(re: &[Re], ec_num: usize, ec: [u8; 256], limits: &Limits) -> Result<Nfa, LimitError> {
     let mut nfa = Nfa { nodes: vec![NfaNode::new(None, SmallVec::new(), HashMap::default())], e_close: [].into(), ec_num, ec };
+    let mut starts = Vec::with_capacity(re.len());
     for (id, re) in re.iter().enumerate() {
       let old_len = nfa.nodes.len();
-      nfa.generate(&re, Some(id as u32));
+      starts.push(old_len);
+      if let Err(e) = nfa.generate(re, Some(id as u32), limits) { return Err(nfa.construction_error(e, limits, &starts)); }
       nfa.nodes[0].eps_edges.push(old_len as u32);
     }
-    nfa.compute_e_close();
-    nfa
+    Ok(nfa)
   }
 
+  // every pattern has at least one accepting state, so it is max accept id + 1
+  pub fn pattern_num(&self) -> usize { self.nodes.iter().filter_map(|n| n.id).max().map_or(0, |id| id as usize + 1) }
+
   // a modified version of Thompson construction, remove some useless state
   // the nfa generated from `generate(re, Some(id))` always start at state 0, and accept at state `nodes.len() - 1`
-  fn generate(&mut self, re: &Re, id: Option<u32>) {
+  // Err comes from too many nodes, or the dfas compiled for `~` and `&`, see `compile_dfa`
+  fn generate(&mut self, re: &Re, id: Option<u32>, limits: &Limits) -> Result<(), LimitError> {
     let start = self.nodes.len();
     match re {
       Re::Eps => self.nodes.push(NfaNode::new(None, smallvec![start as u32 + 1], HashMap::default())),
@@ -105,13 +129,13 @@ impl Nfa {
         edges.insert(self.ec[c as usize], smallvec![start as u32 + 1]);
         self.nodes.push(NfaNode::new(None, SmallVec::new(), edges));
       }
-      Re::Concat(c) => for sub in c.iter() { self.generate(sub, None); }
+      Re::Concat(c) => for sub in c.iter() { self.generate(sub, None, limits)?; }
       Re::Disjunction(d) => unsafe {
         const END: u32 = !0;
         self.nodes.push(NfaNode::new(None, SmallVec::new(), HashMap::default()));
         for sub in d.iter() {
           let old_len = self.nodes.len();
-          self.generate(sub, None);
+          self.generate(sub, None, limits)?;
           let new_len = self.nodes.len();
           for node in self.nodes.get_unchecked_mut(old_len..) {
             for outs in node.edges.values_mut().chain(Some(&mut node.eps_edges)) {
@@ -147,33 +171,161 @@ impl Nfa {
         self.nodes.push(NfaNode::new(None, SmallVec::new(), edges));
       }
       Re::Kleene(k) => {
-        self.generate(k, None);
+        self.generate(k, None, limits)?;
         let end = self.nodes.len() as u32 + 1;
         unsafe { self.nodes.get_unchecked_mut(start) }.eps_edges.push(end);
         self.nodes.push(NfaNode::new(id, smallvec![start as u32, end], HashMap::default()));
       }
+      Re::Complement(x) => self.generate_dfa(std::slice::from_ref(&**x), |acc| !acc[0], limits)?,
+      Re::Intersection(x) => self.generate_dfa(x, |acc| acc.iter().all(|&a| a), limits)?,
     }
     if id.is_some() {
       self.nodes.push(NfaNode::new(id, SmallVec::new(), HashMap::default()));
     }
+    self.check_nodes(limits)
+  }
+
+  // compile `re` into a minimized dfa with the same ec as `self`, a dfa state is accepting iff `f(acc)`,
+  // where `acc[i]` means whether the state accepts `re[i]`, the accept id is always 0
+  // both the nfa of `re` and the subset construction are checked against `limits`, `e.patterns` is left for the caller to fill
+  pub(crate) fn compile_dfa(&self, re: &[Re], f: impl Fn(&[bool]) -> bool, limits: &Limits) -> Result<Dfa, LimitError> {
+    let nfa = Nfa::from_re_with_ec(re, self.ec_num, self.ec, limits)?;
+    nfa.check_limits(limits)?;
+    let (mut dfa, sets) = Dfa::from_nfa_limited(&nfa, limits)?;
+    let mut acc = vec![false; re.len()];
+    for (node, set) in dfa.nodes.iter_mut().zip(&sets) {
+      acc.iter_mut().for_each(|a| *a = false);
+      bitset::ibs(set).ones(|i| if let Some(id) = nfa.nodes[i].id { acc[id as usize] = true; });
+      node.0 = if f(&acc) { Some(0) } else { None };
+    }
+    dfa.minimize();
+    Ok(dfa)
   }
 
+  // see `compile_dfa`, the states of the dfa are embedded here as a fragment like those generated above
+  fn generate_dfa(&mut self, re: &[Re], f: impl Fn(&[bool]) -> bool, limits: &Limits) -> Result<(), LimitError> {
+    let dfa = self.compile_dfa(re, f, limits)?;
+    // dfa states may go back to the dfa start state, but `Disjunction` requires no edge to go back to the fragment start
+    // so an extra state is used as the fragment start
+    let start = self.nodes.len() as u32 + 1;
+    let end = start + dfa.nodes.len() as u32;
+    self.nodes.push(NfaNode::new(None, smallvec![start], HashMap::default()));
+    for (acc, edges) in dfa.nodes {
+      let edges = edges.into_iter().map(|(k, out)| (k, smallvec![start + out])).collect();
+      self.nodes.push(NfaNode::new(None, if acc.is_some() { smallvec![end] } else { SmallVec::new() }, edges));
+    }
+    Ok(())
+  }
+
+  // the eps closures are computed on the condensation of eps edges, whose sccs are found by Tarjan's algorithm
+  // they are found in reverse topological order, so the closure of each scc is computed only once from its successors
+  // O(n * (n + e) / 32) time, and O(n * n / 32) memory for `e_close`
   pub fn compute_e_close(&mut self) {
+    const INVALID: u32 = !0;
     let len = self.nodes.len();
     let elem_len = bitset::bslen(len);
-    self.e_close = vec![0; elem_len * len].into();
-    let p = self.e_close.as_mut_ptr();
-    for i in 0..len {
-      unsafe { bitset::ubs1(p.add(i * elem_len)).set(i); }
+    let mut e_close = vec![0; elem_len * len];
+    let (mut index, mut low, mut on_stack) = (vec![INVALID; len], vec![0; len], vec![false; len]);
+    // `call` simulates the recursion, (node, the index of the next eps edge to visit)
+    let (mut stack, mut call, mut scc, mut row) = (Vec::new(), Vec::new(), Vec::new(), vec![0; elem_len]);
+    let mut cnt = 0;
+    for root in 0..len {
+      if index[root] != INVALID { continue; }
+      call.push((root, 0));
+      while let Some((x, ei)) = call.last_mut() {
+        let x = *x;
+        if index[x] == INVALID {
+          index[x] = cnt;
+          low[x] = cnt;
+          cnt += 1;
+          stack.push(x);
+          on_stack[x] = true;
+        }
+        if let Some(&y) = self.nodes[x].eps_edges.get(*ei) {
+          *ei += 1;
+          let y = y as usize;
+          if index[y] == INVALID {
+            call.push((y, 0));
+          } else if on_stack[y] {
+            low[x] = low[x].min(index[y]);
+          }
+          continue;
+        }
+        call.pop();
+        if let Some(&(parent, _)) = call.last() { low[parent] = low[parent].min(low[x]); }
+        if low[x] == index[x] {
+          // all the sccs reachable from this one are finished, and the nodes in this scc have empty closures now,
+          // so the closure of this scc is simply the union of its nodes and the closures of their successors
+          let pos = stack.iter().rposition(|&y| y == x).unwrap();
+          scc.clear();
+          scc.extend(stack.drain(pos..));
+          row.iter_mut().for_each(|r| *r = 0);
+          for &y in &scc {
+            on_stack[y] = false;
+            bitset::bs(&mut row).set(y);
+            for &z in &self.nodes[y].eps_edges {
+              let z = z as usize;
+              bitset::bs(&mut row).or(&e_close[z * elem_len..(z + 1) * elem_len]);
+            }
+          }
+          for &y in &scc { e_close[y * elem_len..(y + 1) * elem_len].copy_from_slice(&row); }
+        }
+      }
     }
-    loop {
-      let mut changed = false;
-      for (i, node) in self.nodes.iter_mut().enumerate().rev() {
-        for &out in &node.eps_edges {
-          unsafe { changed |= bitset::ubs1(p.add(i * elem_len)).or(p.add(out as usize * elem_len), elem_len); }
+    self.e_close = e_close.into();
+  }
+
+  // add the eps closure of node `x` to `set` by searching eps edges, used when `e_close` is empty
+  // the eps closure of each node already in `set` should also be in `set`, so they are not searched again
+  pub fn add_e_close(&self, set: &mut [u32], x: u32, stack: &mut Vec<u32>) {
+    unsafe {
+      let set = bitset::ubs(set);
+      if set.get(x as usize) { return; }
+      set.set(x as usize);
+      stack.push(x);
+      while let Some(x) = stack.pop() {
+        for &y in &self.nodes[x as usize].eps_edges {
+          if !set.get(y as usize) {
+            set.set(y as usize);
+            stack.push(y);
+          }
         }
       }
-      if !changed { break; }
     }
   }
-}
\ No newline at end of file
+
+  // the eps closure of start state
+  pub(crate) fn start_set(&self, stack: &mut Vec<u32>) -> Box<[u32]> {
+    let elem_len = bitset::bslen(self.nodes.len());
+    if self.e_close.is_empty() {
+      let mut start = Box::<[u32]>::from(vec![0; elem_len]);
+      self.add_e_close(&mut start, 0, stack);
+      start
+    } else { self.e_close[..elem_len].into() }
+  }
+
+  // set `out` to the eps closure of the nodes reachable from `set` via an edge of class `k`
+  pub(crate) fn step(&self, set: &[u32], k: u8, out: &mut [u32], stack: &mut Vec<u32>) {
+    let (nodes, e_close, elem_len) = (self.nodes.as_ptr(), self.e_close.as_ptr(), out.len());
+    let lazy = self.e_close.is_empty();
+    bitset::bs(out).clear();
+    bitset::ibs(set).ones(|i| unsafe {
+      if let Some(outs) = (*nodes.add(i)).edges.get(&k) {
+        for &x in outs {
+          if lazy {
+            self.add_e_close(out, x, stack);
+          } else {
+            bitset::ubs(out).or(e_close.add(x as usize * elem_len), elem_len);
+          }
+        }
+      }
+    });
+  }
+
+  // the accept id of a set of nodes, the node with the smallest index wins
+  pub(crate) fn set_acc(&self, set: &[u32]) -> Option<u32> {
+    let mut id = None;
+    bitset::ibs(set).ones(|i| if id.is_none() { id = self.nodes[i].id; });
+    id
+  }
+}
@@ -19,7 +19,8 @@ fn pretty_u8s<'a>(chs: &'a [u8]) -> impl Display + 'a {
   })
 }
 
-fn print_dot<'a, T: 'a, I>(ec_num: usize, ec: &[u8; 256], nodes: &'a [T], node_attr: impl Fn(&'a T) -> (Option<u32>, I) + 'a)
+// if `tokens` is not None, show token names instead of accept ids
+fn print_dot<'a, T: 'a, I>(ec_num: usize, ec: &[u8; 256], nodes: &'a [T], tokens: Option<&'a TokenSet>, node_attr: impl Fn(&'a T) -> (Option<u32>, I) + 'a)
                            -> impl Display + 'a where I: IntoIterator<Item=(Option<u8>, &'a [u32])> {
   let mut rev_ec = vec![vec![]; ec_num];
   for (idx, &ec) in ec.iter().enumerate() {
@@ -43,9 +44,10 @@ fn print_dot<'a, T: 'a, I>(ec_num: usize, ec: &[u8; 256], nodes: &'a [T], node_a
         edge.sort_unstable();
         writeln!(f, r#"{} -> {} [label="{}"];"#, idx, out, pretty_u8s(&edge))?;
       }
-      match id {
-        Some(id) => writeln!(f, r#"{}[shape=doublecircle, label="{0}\nacc:{}"]"#, idx, id)?,
-        None => writeln!(f, r#"{}[shape=circle, label="{0}"]"#, idx)?,
+      match (id, tokens) {
+        (Some(id), Some(tokens)) => writeln!(f, r#"{}[shape=doublecircle, label="{0}\nacc:{}"]"#, idx, tokens.name(id))?,
+        (Some(id), None) => writeln!(f, r#"{}[shape=doublecircle, label="{0}\nacc:{}"]"#, idx, id)?,
+        (None, _) => writeln!(f, r#"{}[shape=circle, label="{0}"]"#, idx)?,
       };
     }
     f.write_str("}")
@@ -54,15 +56,20 @@ fn print_dot<'a, T: 'a, I>(ec_num: usize, ec: &[u8; 256], nodes: &'a [T], node_a
 
 impl Nfa {
   pub fn print_dot<'a>(&'a self) -> impl Display + 'a {
-    print_dot(self.ec_num, &self.ec, &self.nodes, |node| {
+    print_dot(self.ec_num, &self.ec, &self.nodes, None, |node| {
       (node.id, node.edges.iter().map(|(&k, v)| (Some(k), v.as_ref())).chain(Some((None, node.eps_edges.as_ref()))))
     })
   }
 }
 
 impl Dfa {
-  pub fn print_dot<'a>(&'a self) -> impl Display + 'a {
-    print_dot(self.ec_num, &self.ec, &self.nodes, |(id, edges)| {
+  pub fn print_dot<'a>(&'a self) -> impl Display + 'a { self.print_dot_impl(None) }
+
+  // `self` should be built from `tokens`
+  pub fn print_dot_with<'a>(&'a self, tokens: &'a TokenSet) -> impl Display + 'a { self.print_dot_impl(Some(tokens)) }
+
+  fn print_dot_impl<'a>(&'a self, tokens: Option<&'a TokenSet>) -> impl Display + 'a {
+    print_dot(self.ec_num, &self.ec, &self.nodes, tokens, |(id, edges)| {
       (*id, edges.iter().map(|(&k, v)| (Some(k), std::slice::from_ref(v))))
     })
   }
@@ -0,0 +1,128 @@
+use std::fmt;
+use crate::{*, analysis::{escape, ec_repr}};
+
+// a state of the product automaton, None stands for the dead state
+type Pair = (Option<u32>, Option<u32>);
+
+// the equivalence classes of a product automaton: two bytes are in the same class iff they are in the same class in both dfas
+pub(crate) fn merge_ec(l: &[u8; 256], r: &[u8; 256]) -> (usize, [u8; 256]) {
+  let mut ec = [0; 256];
+  let mut id = HashMap::default();
+  for ch in 0..256 {
+    let new = id.len() as u8;
+    ec[ch] = *id.entry((l[ch], r[ch])).or_insert(new);
+  }
+  (id.len(), ec)
+}
+
+// the reachable part of the product of `l` and `r`, states are numbered in bfs order and their acc are all None
+// `pairs[i]` is the pair of states that state i stands for, and (None, None) is omitted
+pub(crate) fn product(l: &Dfa, r: &Dfa) -> (Dfa, Vec<Pair>) {
+  let (ec_num, ec) = merge_ec(&l.ec, &r.ec);
+  let repr = ec_repr(&ec, ec_num);
+  let mut pairs = vec![(Some(0), Some(0))];
+  let mut ids = HashMap::default();
+  ids.insert(pairs[0], 0);
+  let mut nodes = Vec::new();
+  while nodes.len() < pairs.len() {
+    let (s1, s2) = pairs[nodes.len()];
+    let mut link = HashMap::default();
+    for (k, &ch) in repr.iter().enumerate() {
+      let next = (s1.and_then(|s| l.next(s, ch)), s2.and_then(|s| r.next(s, ch)));
+      if next == (None, None) { continue; }
+      let id = pairs.len() as u32;
+      let id = *ids.entry(next).or_insert_with(|| {
+        pairs.push(next);
+        id
+      });
+      link.insert(k as u8, id);
+    }
+    nodes.push((None, link));
+  }
+  (Dfa { nodes, ec_num, ec }, pairs)
+}
+
+// the accept ids of the two states in `p`
+fn pair_acc(l: &Dfa, r: &Dfa, p: Pair) -> Pair { (p.0.and_then(|s| l.acc(s)), p.1.and_then(|s| r.acc(s))) }
+
+// a string on which two dfas disagree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counterexample {
+  pub input: Vec<u8>,
+  // the pattern that accepts `input` in the left / right dfa
+  pub left: Option<u32>,
+  pub right: Option<u32>,
+}
+
+impl fmt::Display for Counterexample {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let acc = |id: Option<u32>| fn2display(move |f| match id {
+      Some(id) => write!(f, "pattern {}", id),
+      None => f.write_str("nothing"),
+    });
+    write!(f, "on {}, the left dfa accepts {}, but the right dfa accepts {}", escape(&self.input), acc(self.left), acc(self.right))
+  }
+}
+
+impl Dfa {
+  // check whether `self` and `other` accept the same strings with the same patterns, up to a renaming of accept ids,
+  // which must be one-to-one, e.g., [a, b] and [b, a] are equivalent, but [a, b] and [a|b] are not
+  // the renaming is not guessed: the first string accepted with id `a` in `self` forces `a` to be renamed to the id
+  // `other` accepts it with, so it is inferred from the strings in bfs order, and Err(c) means `c.input` is the shortest string
+  // on which the two dfas disagree, either on whether it is accepted, or with the renaming forced by shorter strings
+  // neither dfa needs to be minimized
+  pub fn equivalent(&self, other: &Dfa) -> Result<(), Counterexample> {
+    let (prod, pairs) = product(self, other);
+    let (mut l2r, mut r2l) = (HashMap::default(), HashMap::default());
+    // states are in bfs order, so the first state found to disagree gives the shortest counterexample
+    for (i, &p) in pairs.iter().enumerate() {
+      let (left, right) = pair_acc(self, other, p);
+      let agree = match (left, right) {
+        (Some(a), Some(b)) => *l2r.entry(a).or_insert(b) == b && *r2l.entry(b).or_insert(a) == a,
+        _ => left == right,
+      };
+      if !agree {
+        let input = Dfa::path(&prod.ec_repr(), &prod.bfs_parent(), i as u32);
+        return Err(Counterexample { input, left, right });
+      }
+    }
+    Ok(())
+  }
+
+  // `f` maps the accept ids of `self` and `other` on a string to the accept id of the result on it
+  // the result is minimized, and neither dfa needs to be minimized
+  fn combine(&self, other: &Dfa, f: impl Fn(Option<u32>, Option<u32>) -> Option<u32>) -> Dfa {
+    let (mut dfa, pairs) = product(self, other);
+    for (node, &p) in dfa.nodes.iter_mut().zip(&pairs) {
+      let (l, r) = pair_acc(self, other, p);
+      node.0 = f(l, r);
+    }
+    dfa.minimize();
+    dfa
+  }
+
+  // in the following operations, the accept id of a string in the result is the id in `self` if `self` accepts it,
+  // otherwise the id in `other`, the ids of `other` are not renumbered, so they may coincide with those of `self`
+
+  // the strings accepted by both
+  pub fn intersection(&self, other: &Dfa) -> Dfa { self.combine(other, |l, r| r.and(l)) }
+
+  // the strings accepted by either
+  pub fn union(&self, other: &Dfa) -> Dfa { self.combine(other, |l, r| l.or(r)) }
+
+  // the strings accepted by `self` but not by `other`, e.g., identifiers minus keywords
+  pub fn difference(&self, other: &Dfa) -> Dfa { self.combine(other, |l, r| l.filter(|_| r.is_none())) }
+
+  // the strings accepted by exactly one of them
+  pub fn symmetric_difference(&self, other: &Dfa) -> Dfa { self.combine(other, |l, r| l.xor(r)) }
+
+  // check whether all the strings accepted by `self` are accepted by `other`, accept ids are ignored
+  // Err(s) means `s` is the shortest string accepted by `self` but not by `other`
+  pub fn is_subset(&self, other: &Dfa) -> Result<(), Vec<u8>> {
+    let (prod, pairs) = product(self, other);
+    match pairs.iter().position(|&p| matches!(pair_acc(self, other, p), (Some(_), None))) {
+      Some(i) => Err(Dfa::path(&prod.ec_repr(), &prod.bfs_parent(), i as u32)),
+      None => Ok(()),
+    }
+  }
+}
@@ -1,89 +1,309 @@
-use nom::{branch::alt, bytes::complete::tag, combinator::{map, cut}, multi::{separated_list0, many1}, sequence::{preceded, terminated, tuple}, Err, error::{ErrorKind, Error}, IResult};
+use nom::{branch::alt, bytes::complete::tag, combinator::map, multi::{separated_list0, separated_list1, many1}, sequence::{terminated, tuple}, Err, error::ErrorKind};
+use std::fmt;
 use crate::*;
+use ParseErrorKind::*;
 
-#[derive(Debug, Clone)]
+// Eq, Ord and Hash are structural, they are used to identify states in `Dfa::from_re`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Re {
   Eps,
   Ch(u8),
   // theoretically Concat & Disjunction only need 2 children, but using Vec here can make future analysis faster
+  // Concat, Disjunction and Intersection should have at least 1 child, the parser always gives at least 2
   Concat(Box<[Re]>),
   Disjunction(Box<[Re]>),
   // DisjunctionCh(bitset) == Disjunction([(ones in bitset).map(Ch)])
   // this is a more efficient way to represent [] in regex
   DisjunctionCh(Box<[u32; 8]>),
   Kleene(Box<Re>),
+  // the strings not accepted by the child, i.e., `~r` in regex
+  Complement(Box<Re>),
+  // the strings accepted by all children, i.e., `r&s` in regex
+  Intersection(Box<[Re]>),
 }
 
-// our simple implementation doesn't support {n},^,$, but still regard them as meta chars
-const META: &[u8] = br"()[].|*+\{}^$?";
+// our simple implementation doesn't support ^,$, but still regard them as meta chars
+const META: &[u8] = br"()[].|*+\{}^$?~&";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorKind {
+  // `(` without matching `)`, offset points to `(`
+  UnclosedGroup,
+  // `[` without matching `]`, offset points to `[`
+  UnclosedClass,
+  // `)` without matching `(`
+  UnmatchedParen,
+  // `*`, `+`, `?` or `{` with nothing to repeat, e.g., at the beginning of a group, right after `|`, `&` or `~`,
+  // or after another quantifier, offset points to the quantifier
+  DanglingQuantifier,
+  // `|` with nothing on one of its sides
+  EmptyAlternative,
+  // `&` with nothing on one of its sides, or `~` with nothing to apply to, offset points to `&` or `~`
+  EmptyOperand,
+  // `\x` not followed by 2 hex digits, offset points to `\`
+  InvalidHexEscape,
+  // `\` followed by a char that can't be escaped, offset points to `\`
+  InvalidEscape,
+  // malformed {n}, {n,}, {m,n}, or the counts are too large, or m > n, offset points to `{`
+  InvalidRepeat,
+  // a range in [] with an endpoint like `\d`, e.g., `[a-\d]` or `[\d-z]`, offset points to that endpoint
+  InvalidRange,
+  // any other char that can't appear here, e.g., unescaped `]`, `}`, `[` inside []
+  UnexpectedChar,
+}
+
+// the syntax error in `re[idx]` at `re[idx][offset]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseError {
+  pub idx: usize,
+  pub offset: usize,
+  pub kind: ParseErrorKind,
+}
+
+impl fmt::Display for ParseErrorKind {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(match self {
+      UnclosedGroup => "unclosed group",
+      UnclosedClass => "unclosed character class",
+      UnmatchedParen => "unmatched `)`",
+      DanglingQuantifier => "dangling quantifier",
+      EmptyAlternative => "empty alternative",
+      EmptyOperand => "empty operand",
+      InvalidHexEscape => "invalid hex escape",
+      InvalidEscape => "invalid escape",
+      InvalidRepeat => "invalid repetition",
+      InvalidRange => "invalid range",
+      UnexpectedChar => "unexpected character",
+    })
+  }
+}
+
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{} at byte {} of pattern {}", self.kind, self.offset, self.idx)
+  }
+}
+
+impl std::error::Error for ParseError {}
+
+// the error type used in nom parsers, `.0` is the remaining input where error occurs
+struct Error<I>(I, ParseErrorKind);
+
+impl<I> nom::error::ParseError<I> for Error<I> {
+  fn from_error_kind(i: I, _: ErrorKind) -> Self { Error(i, UnexpectedChar) }
+
+  // the innermost error is the most accurate one
+  fn append(_: I, _: ErrorKind, other: Self) -> Self { other }
+}
+
+type IResult<I, O> = nom::IResult<I, O, Error<I>>;
 
 macro_rules! err {
-  ($i: expr, $code: ident) => { Err(Err::Error(Error::new($i, ErrorKind::$code))) };
+  ($i: expr, $kind: ident) => { Err(Err::Error(Error($i, $kind))) };
 }
 
-fn escaped_ascii<'a>(i: &'a [u8]) -> IResult<&'a [u8], u8> {
+macro_rules! fail {
+  ($i: expr, $kind: ident) => { Err(Err::Failure(Error($i, $kind))) };
+}
+
+fn escaped_ascii(i: &[u8]) -> IResult<&[u8], u8> {
   alt((
     map(tag(br#"\""#), |_| b'\"'),
     map(tag(br"\\"), |_| b'\\'),
     map(tag(br"\n"), |_| b'\n'),
     map(tag(br"\t"), |_| b'\t'),
     map(tag(br"\r"), |_| b'\r'),
-    preceded(tag(br"\x"), |i: &'a [u8]| {
-      if let [hi, lo, ref i @ ..] = i {
+    escaped_hex,
+  ))(i)
+}
+
+fn escaped_hex(i: &[u8]) -> IResult<&[u8], u8> {
+  match i {
+    [b'\\', b'x', ref i1 @ ..] => {
+      if let [hi, lo, ref i1 @ ..] = i1 {
         let hex = |x| match x {
           b'0'..=b'9' => Some(x - b'0'), b'a'..=b'f' => Some(x - b'a' + 10), b'A'..=b'F' => Some(x - b'A' + 10), _ => None
         };
-        if let (Some(hi), Some(lo)) = (hex(*hi), hex(*lo)) { return Ok((i, hi * 16 + lo)); }
+        if let (Some(hi), Some(lo)) = (hex(*hi), hex(*lo)) { return Ok((i1, hi * 16 + lo)); }
       }
-      err!(i, HexDigit)
-    }),
-  ))(i)
+      fail!(i, InvalidHexEscape)
+    }
+    _ => err!(i, UnexpectedChar),
+  }
 }
 
 #[inline(always)]
 fn byte(b: u8) -> impl Fn(&[u8]) -> IResult<&[u8], ()> {
-  move |i| { match i { [x, ref i @ ..] if *x == b => Ok((i, ())), _ => err!(i, Char) } }
+  move |i| { match i { [x, ref i @ ..] if *x == b => Ok((i, ())), _ => err!(i, UnexpectedChar) } }
 }
 
 #[inline(always)]
 fn none_of(s: &'static [u8]) -> impl Fn(&[u8]) -> IResult<&[u8], u8> {
-  move |i| { match i { [x, ref i @ ..] if !s.contains(x) => Ok((i, *x)), _ => err!(i, NoneOf) } }
+  move |i| { match i { [x, ref i @ ..] if !s.contains(x) => Ok((i, *x)), _ => err!(i, UnexpectedChar) } }
 }
 
-#[inline(always)]
-fn one_of(s: &'static [u8]) -> impl Fn(&[u8]) -> IResult<&[u8], u8> {
-  move |i| { match i { [x, ref i @ ..] if s.contains(x) => Ok((i, *x)), _ => err!(i, OneOf) } }
+// classify the error when `i` is not empty, but the parser can't go further
+fn unexpected(i: &[u8]) -> Error<&[u8]> {
+  Error(i, match i {
+    [b'*', ..] | [b'+', ..] | [b'?', ..] | [b'{', ..] => DanglingQuantifier,
+    // e.g., `a|*`, the quantifier is the real problem, rather than the empty alternative
+    [b'|', q, ..] | [b'&', q, ..] if b"*+?{".contains(q) => return Error(&i[1..], DanglingQuantifier),
+    [b'|', ..] => EmptyAlternative,
+    [b'&', ..] => EmptyOperand,
+    [b')', ..] => UnmatchedParen,
+    _ => UnexpectedChar,
+  })
+}
+
+// the set of b'0'..=b'9'
+const DIGIT: [u32; 8] = [0, 0b11111111110000000000000000, 0, 0, 0, 0, 0, 0];
+// the set of (b'0'..=b'9').chain(b'a'..=b'z').chain(b'A'..=b'Z').chain(Some(b'_'))
+const WORD: [u32; 8] = [0, 0b11111111110000000000000000, 0b10000111111111111111111111111110, 0b111111111111111111111111110, 0, 0, 0, 0];
+// the set of "\n\t\r ".bytes()
+const SPACE: [u32; 8] = [0b10011000000000, 0b1, 0, 0, 0, 0, 0, 0];
+
+// \d, \w, \s and their negations \D, \W, \S, they can be used both inside and outside []
+fn shorthand(i: &[u8]) -> IResult<&[u8], [u32; 8]> {
+  let (i, mut set, inv) = match i {
+    [b'\\', x, ref i @ ..] => match x {
+      b'd' => (i, DIGIT, false), b'D' => (i, DIGIT, true),
+      b'w' => (i, WORD, false), b'W' => (i, WORD, true),
+      b's' => (i, SPACE, false), b'S' => (i, SPACE, true),
+      _ => return err!(i, UnexpectedChar),
+    }
+    _ => return err!(i, UnexpectedChar),
+  };
+  if inv { bitset::bs(&mut set).inv(); }
+  Ok((i, set))
 }
 
 fn atom(i: &[u8]) -> IResult<&[u8], Re> {
   alt((
     map(none_of(META), Ch),
     map(escaped_ascii, Ch),
-    // equivalent to `Disjunction((b'0'..=b'9').map(Ch).collect())`
-    map(tag(br"\d"), |_| DisjunctionCh([0, 0b11111111110000000000000000, 0, 0, 0, 0, 0, 0].into())),
-    // equivalent to `Disjunction((b'0'..=b'9').chain(b'..=b'z').chain(b'..=b'Z').chain(Some(b'_')).map(Ch).collect())`
-    map(tag(br"\w"), |_| DisjunctionCh([0, 0b11111111110000000000000000, 0b10000111111111111111111111111110, 0b111111111111111111111111110, 0, 0, 0, 0].into())),
-    // equivalent to `Disjunction("\n\t\r ".bytes().map(Ch).collect())`
-    map(tag(br"\s"), |_| DisjunctionCh([0b10011000000000, 0b1, 0, 0, 0, 0, 0, 0].into())),
+    map(shorthand, |set| DisjunctionCh(set.into())),
     // equivalent to `Disjunction((0..=255).map(Ch).collect())`
     map(byte(b'.'), |_| DisjunctionCh([!0; 8].into())),
-    preceded(byte(b'\\'), map(cut(one_of(META)), Ch)),
-    preceded(byte(b'('), cut(terminated(re, byte(b')')))),
+    escaped_meta,
+    group,
     range,
   ))(i)
 }
 
+fn escaped_meta(i: &[u8]) -> IResult<&[u8], Re> {
+  match i {
+    [b'\\', x, ref i1 @ ..] if META.contains(x) => Ok((i1, Ch(*x))),
+    [b'\\', ..] => fail!(i, InvalidEscape),
+    _ => err!(i, UnexpectedChar),
+  }
+}
+
+fn group(i: &[u8]) -> IResult<&[u8], Re> {
+  let (i1, _) = byte(b'(')(i)?;
+  match re(i1)? {
+    ([b')', ref i1 @ ..], re) => Ok((i1, re)),
+    ([], _) => fail!(i, UnclosedGroup),
+    (i1, _) => Err(Err::Failure(unexpected(i1))),
+  }
+}
+
+// the counts in {n}, {n,}, {m,n} can't exceed `MAX_REPEAT`
+const MAX_REPEAT: usize = 1000;
+// the number of `Re` nodes generated by expanding a repetition can't exceed `MAX_EXPAND`
+// without this limit, things like `((a{1000}){1000}){1000}` will exhaust memory
+const MAX_EXPAND: usize = 100000;
+
+impl Re {
+  pub(crate) fn size(&self) -> usize {
+    match self {
+      Concat(x) | Disjunction(x) | Intersection(x) => 1 + x.iter().map(Re::size).sum::<usize>(),
+      Kleene(x) | Complement(x) => 1 + x.size(),
+      _ => 1,
+    }
+  }
+}
+
+fn count(i: &[u8]) -> IResult<&[u8], usize> {
+  let len = i.iter().position(|x| !x.is_ascii_digit()).unwrap_or(i.len());
+  if len == 0 { return err!(i, InvalidRepeat); }
+  // a long enough digit sequence always exceeds `MAX_REPEAT`, don't bother parsing it
+  let n = i[..len].iter().try_fold(0usize, |acc, &x| if acc > MAX_REPEAT { None } else { Some(acc * 10 + (x - b'0') as usize) });
+  match n {
+    Some(n) if n <= MAX_REPEAT => Ok((&i[len..], n)),
+    _ => err!(i, InvalidRepeat),
+  }
+}
+
+// parse the part after `{`, return (m, n), n == None means no upper bound
+fn repeat_range(i: &[u8]) -> IResult<&[u8], (usize, Option<usize>)> {
+  let (i, lo) = count(i)?;
+  let (i, hi) = match i {
+    [b',', b'}', ref i @ ..] => (i, None),
+    [b',', ref i @ ..] => terminated(map(count, Some), byte(b'}'))(i)?,
+    [b'}', ref i @ ..] => (i, Some(lo)),
+    _ => return err!(i, InvalidRepeat),
+  };
+  match hi { Some(hi) if hi < lo => err!(i, InvalidRepeat), _ => Ok((i, (lo, hi))) }
+}
+
+// a{m,n} == a...a (a (a ...)?)?, with m copies of a outside
+fn repeat(a: Re, lo: usize, hi: Option<usize>) -> Re {
+  let mut c = vec![a.clone(); lo];
+  match hi {
+    None => c.push(Kleene(Box::new(a))),
+    Some(hi) => {
+      let mut opt = None;
+      for _ in lo..hi {
+        opt = Some(Disjunction([Eps, match opt {
+          None => a.clone(),
+          Some(opt) => Concat([a.clone(), opt].into()),
+        }].into()));
+      }
+      c.extend(opt);
+    }
+  }
+  match c.len() { 0 => Eps, 1 => c.remove(0), _ => Concat(c.into()) }
+}
+
 fn atom_with_suffix(i: &[u8]) -> IResult<&[u8], Re> {
   let (i, a) = atom(i)?;
   Ok(match i {
     [b'*', ref i @ ..] => (i, Kleene(Box::new(a))),
     [b'+', ref i @ ..] => (i, Concat([a.clone(), Kleene(Box::new(a))].into())),
     [b'?', ref i @ ..] => (i, Disjunction([Eps, a].into())),
+    [b'{', ref i1 @ ..] => {
+      let (i1, (lo, hi)) = match repeat_range(i1) { Ok(x) => x, Err(_) => return fail!(i, InvalidRepeat) };
+      if a.size() * hi.unwrap_or(lo).max(lo + 1) > MAX_EXPAND { return fail!(i, InvalidRepeat); }
+      (i1, repeat(a, lo, hi))
+    }
     _ => (i, a),
   })
 }
 
+// `~` applies to the following factor, e.g., `~ab` == `(~a)b`, `~a*` == `~(a*)`
+fn factor(i: &[u8]) -> IResult<&[u8], Re> {
+  match i {
+    [b'~', ref i1 @ ..] => match factor(i1) {
+      Ok((i1, a)) => Ok((i1, Complement(Box::new(a)))),
+      Err(Err::Error(_)) => match i1 {
+        [q, ..] if b"*+?{".contains(q) => fail!(i1, DanglingQuantifier),
+        _ => fail!(i, EmptyOperand),
+      }
+      Err(e) => Err(e),
+    }
+    _ => atom_with_suffix(i),
+  }
+}
+
+// the precedence from high to low is: suffix, `~`, concatenation, `&`, `|`
+fn intersection(i: &[u8]) -> IResult<&[u8], Re> {
+  let concat = map(many1(factor), |mut c| match c.len() { 1 => c.remove(0), _ => Concat(c.into()) });
+  map(separated_list1(byte(b'&'), concat), |mut c| match c.len() { 1 => c.remove(0), _ => Intersection(c.into()) })(i)
+}
+
 // meta characters are not escaped here, but other normal ascii escape chars and [] are
+// \d, \w, \s, \D, \W, \S are handled separately in `range`
 // multi-byte char is not supported in []
 fn ascii_no_bracket(i: &[u8]) -> IResult<&[u8], u8> {
   alt((
@@ -94,34 +314,48 @@ fn ascii_no_bracket(i: &[u8]) -> IResult<&[u8], u8> {
   ))(i)
 }
 
-fn range<'a>(i: &'a [u8]) -> IResult<&'a [u8], Re> {
+fn range(i: &[u8]) -> IResult<&[u8], Re> {
+  let (mut i1, inv) = match i {
+    [b'[', b'^', ref i1 @ ..] => (i1, true),
+    [b'[', ref i1 @ ..] => (i1, false),
+    _ => return err!(i, UnexpectedChar),
+  };
   // basically copied from `nom::multi::many1`, but avoid allocating a Vec as the result
-  preceded(byte(b'['), cut(terminated(|i: &'a [u8]| {
-    let (mut i, inv) = match i { [b'^', ref i @ ..] => (i, true), _ => (i, false) };
-    let mut set = [0; 8];
-    loop {
-      match alt((
-        map(tuple((ascii_no_bracket, byte(b'-'), ascii_no_bracket)), |(l, _, u)| (l, u)),
-        map(ascii_no_bracket, |x| (x, x)),
-      ))(i) {
-        Err(Err::Error(_)) => break,
-        Err(e) => return Err(e),
-        Ok((i1, (l, u))) => {
-          i = i1;
-          for x in l..=u { bitset::bs(&mut set).set(x as usize); }
-        }
+  let mut set = [0; 8];
+  loop {
+    if let Ok((i2, s)) = shorthand(i1) {
+      // a `-` after a shorthand is only allowed as the last char
+      if let [b'-', x, ..] = i2 { if *x != b']' { return fail!(i1, InvalidRange); } }
+      i1 = i2;
+      bitset::bs(&mut set).or(&s);
+      continue;
+    }
+    match alt((
+      map(tuple((ascii_no_bracket, byte(b'-'), ascii_no_bracket)), |(l, _, u)| (l, u)),
+      map(ascii_no_bracket, |x| (x, x)),
+    ))(i1) {
+      Err(Err::Error(_)) => break,
+      Err(e) => return Err(e),
+      Ok((i2, (l, u))) => {
+        if let [b'-', ref i3 @ ..] = i2 { if shorthand(i3).is_ok() { return fail!(i3, InvalidRange); } }
+        i1 = i2;
+        for x in l..=u { bitset::bs(&mut set).set(x as usize); }
       }
     }
-    if inv { bitset::bs(&mut set).inv(); }
-    Ok((i, DisjunctionCh(set.into())))
-  }, byte(b']'))))(i)
+  }
+  if inv { bitset::bs(&mut set).inv(); }
+  match i1 {
+    [b']', ref i1 @ ..] => Ok((i1, DisjunctionCh(set.into()))),
+    [] => fail!(i, UnclosedClass),
+    [b'\\', ..] => fail!(i1, InvalidEscape),
+    _ => fail!(i1, UnexpectedChar),
+  }
 }
 
 fn re(i: &[u8]) -> IResult<&[u8], Re> {
   // currently for a Vec with len == 1, the range check in `.remove(0)` can be optimized out
   // but the check in `.into_iter().next().unwrap()` cannot, so I choose the former
-  let (i, mut d) = separated_list0(byte(b'|'), map(many1(atom_with_suffix), |mut c|
-    match c.len() { 1 => c.remove(0), _ => Concat(c.into()) }))(i)?;
+  let (i, mut d) = separated_list0(byte(b'|'), intersection)(i)?;
   Ok((i, match d.len() {
     0 => Eps, 1 => d.remove(0), _ => {
       let mut set = [0; 8];
@@ -135,10 +369,19 @@ fn re(i: &[u8]) -> IResult<&[u8], Re> {
   }))
 }
 
-pub fn parse(i: &[u8]) -> Result<Re, String> {
-  match re(i) {
-    Ok((b"", result)) => Ok(result),
-    Ok((remain, _)) => Err(format!("remaining part cannot be parsed: {:?}", remain)),
-    Err(e) => Err(format!("{}", e)),
-  }
-}
\ No newline at end of file
+// the returned error always has `idx == 0`, the caller can set it if `i` is one of many patterns
+pub fn parse(i: &[u8]) -> Result<Re, ParseError> {
+  let Error(remain, kind) = match re(i) {
+    Ok(([], result)) => return Ok(result),
+    Ok((remain, _)) => unexpected(remain),
+    Err(Err::Error(e)) | Err(Err::Failure(e)) => e,
+    // all parsers used here are complete parsers
+    Err(Err::Incomplete(_)) => unreachable!(),
+  };
+  Err(ParseError { idx: 0, offset: i.len() - remain.len(), kind })
+}
+
+// parse each pattern in `re`, the returned error has `idx` set to the index of the invalid pattern
+pub fn parse_all<'a>(re: impl IntoIterator<Item=&'a [u8]>) -> Result<Vec<Re>, ParseError> {
+  re.into_iter().enumerate().map(|(idx, re)| parse(re).map_err(|e| ParseError { idx, ..e })).collect()
+}
@@ -0,0 +1,154 @@
+use serde::{Serialize, Serializer, Deserialize, Deserializer, de::Error};
+use smallvec::SmallVec;
+use std::{collections::BTreeMap, convert::TryInto};
+use crate::*;
+
+// `Re` derives Serialize directly, the other types are converted to these forms, where the maps are sorted
+// so that the output is deterministic, and `ec` is a sequence, because serde doesn't support [u8; 256]
+// deserialization checks the invariants documented on `Re`, `Nfa` and `Dfa`, so the result can be used safely
+
+// the same as `Re`, its children are deserialized as `Re`, so they are checked recursively
+#[derive(Deserialize)]
+#[serde(rename = "Re")]
+enum ReRepr {
+  Eps,
+  Ch(u8),
+  Concat(Box<[Re]>),
+  Disjunction(Box<[Re]>),
+  DisjunctionCh(Box<[u32; 8]>),
+  Kleene(Box<Re>),
+  Complement(Box<Re>),
+  Intersection(Box<[Re]>),
+}
+
+#[derive(Serialize, Deserialize)]
+struct NfaNodeRepr {
+  id: Option<u32>,
+  eps_edges: Vec<u32>,
+  edges: BTreeMap<u8, Vec<u32>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct NfaRepr {
+  nodes: Vec<NfaNode>,
+  e_close: Vec<u32>,
+  ec_num: usize,
+  ec: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DfaRepr {
+  nodes: Vec<(Option<u32>, BTreeMap<u8, u32>)>,
+  ec_num: usize,
+  ec: Vec<u8>,
+}
+
+// check that `ec` has 256 elements, all of which are within [0, ec_num)
+fn check_ec(ec_num: usize, ec: &[u8]) -> Result<[u8; 256], String> {
+  let ec: [u8; 256] = ec.try_into().map_err(|_| format!("ec has {} elements, expect 256", ec.len()))?;
+  if !(1..=256).contains(&ec_num) { return Err(format!("ec_num {} is not within [1, 256]", ec_num)); }
+  match ec.iter().position(|&k| k as usize >= ec_num) {
+    Some(ch) => Err(format!("ec[{}] = {} is not within [0, ec_num)", ch, ec[ch])),
+    None => Ok(ec),
+  }
+}
+
+// check the keys and the targets of the edges of node `i`
+fn check_edge(i: usize, k: u8, to: u32, ec_num: usize, node_num: usize) -> Result<(), String> {
+  if k as usize >= ec_num { return Err(format!("node {} has key {} not within [0, ec_num)", i, k)); }
+  if to as usize >= node_num { return Err(format!("node {} has an edge to node {}, which doesn't exist", i, to)); }
+  Ok(())
+}
+
+impl<'de> Deserialize<'de> for Re {
+  fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+    let check = |name, x: Box<[Re]>| if x.is_empty() { Err(D::Error::custom(format!("{} should have at least 1 child", name))) } else { Ok(x) };
+    Ok(match ReRepr::deserialize(d)? {
+      ReRepr::Eps => Eps,
+      ReRepr::Ch(ch) => Ch(ch),
+      ReRepr::Concat(x) => Concat(check("Concat", x)?),
+      ReRepr::Disjunction(x) => Disjunction(check("Disjunction", x)?),
+      ReRepr::DisjunctionCh(x) => DisjunctionCh(x),
+      ReRepr::Kleene(x) => Kleene(x),
+      ReRepr::Complement(x) => Complement(x),
+      ReRepr::Intersection(x) => Intersection(check("Intersection", x)?),
+    })
+  }
+}
+
+impl Serialize for NfaNode {
+  fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+    let edges = self.edges.iter().map(|(&k, to)| (k, to.to_vec())).collect();
+    NfaNodeRepr { id: self.id, eps_edges: self.eps_edges.to_vec(), edges }.serialize(s)
+  }
+}
+
+impl<'de> Deserialize<'de> for NfaNode {
+  fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+    let r = NfaNodeRepr::deserialize(d)?;
+    let edges = r.edges.into_iter().map(|(k, to)| (k, SmallVec::from_vec(to))).collect();
+    Ok(NfaNode { id: r.id, eps_edges: SmallVec::from_vec(r.eps_edges), edges })
+  }
+}
+
+impl Serialize for Nfa {
+  fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+    // NfaNode is not Clone, so serialize the fields directly instead of building `NfaRepr`
+    #[derive(Serialize)]
+    struct NfaRef<'a> { nodes: &'a [NfaNode], e_close: &'a [u32], ec_num: usize, ec: &'a [u8] }
+    NfaRef { nodes: &self.nodes, e_close: &self.e_close, ec_num: self.ec_num, ec: &self.ec }.serialize(s)
+  }
+}
+
+impl<'de> Deserialize<'de> for Nfa {
+  fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+    let r = NfaRepr::deserialize(d)?;
+    let check = || {
+      let n = r.nodes.len();
+      if n == 0 { return Err("an nfa should have at least 1 node".to_owned()); }
+      let ec = check_ec(r.ec_num, &r.ec)?;
+      for (i, node) in r.nodes.iter().enumerate() {
+        for &to in &node.eps_edges { check_edge(i, 0, to, r.ec_num, n)?; }
+        for (&k, to) in &node.edges {
+          for &to in to.iter() { check_edge(i, k, to, r.ec_num, n)?; }
+        }
+      }
+      let len = bitset::bslen(n);
+      if !r.e_close.is_empty() {
+        if r.e_close.len() != n * len { return Err(format!("e_close has {} elements, expect 0 or {}", r.e_close.len(), n * len)); }
+        // the unused bits in the last element of each row should be 0
+        if n % 32 != 0 && r.e_close.chunks(len).any(|row| row[len - 1] >> (n % 32) != 0) {
+          return Err("e_close contains nodes that don't exist".to_owned());
+        }
+      }
+      Ok(ec)
+    };
+    let ec = check().map_err(D::Error::custom)?;
+    Ok(Nfa { nodes: r.nodes, e_close: r.e_close.into(), ec_num: r.ec_num, ec })
+  }
+}
+
+impl Serialize for Dfa {
+  fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+    let nodes = self.nodes.iter().map(|(acc, link)| (*acc, link.iter().map(|(&k, &to)| (k, to)).collect())).collect();
+    DfaRepr { nodes, ec_num: self.ec_num, ec: self.ec.to_vec() }.serialize(s)
+  }
+}
+
+impl<'de> Deserialize<'de> for Dfa {
+  fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+    let r = DfaRepr::deserialize(d)?;
+    let check = || {
+      let n = r.nodes.len();
+      if n == 0 { return Err("a dfa should have at least 1 node".to_owned()); }
+      let ec = check_ec(r.ec_num, &r.ec)?;
+      for (i, (_, link)) in r.nodes.iter().enumerate() {
+        for (&k, &to) in link { check_edge(i, k, to, r.ec_num, n)?; }
+      }
+      Ok(ec)
+    };
+    let ec = check().map_err(D::Error::custom)?;
+    let nodes = r.nodes.into_iter().map(|(acc, link)| (acc, link.into_iter().collect())).collect();
+    Ok(Dfa { nodes, ec_num: r.ec_num, ec })
+  }
+}
@@ -0,0 +1,134 @@
+use std::fmt;
+use crate::*;
+
+// `TokenSet::from_spec` parses a lexer specification in the following format:
+// ```
+// # definitions section, each line is `NAME pattern`, pattern can use earlier definitions
+// DIGIT  [0-9]
+// ID     [a-zA-Z_]\w*
+// %%
+// # rules section, each line is `pattern NAME`, pattern can use all definitions
+// {DIGIT}+  INT
+// if        IF
+// {ID}      IDENT
+// ```
+// the definitions section and `%%` can be omitted, then all lines are rules
+// empty lines and lines starting with `#` (after leading whitespace) are ignored, use `[#]` for a pattern starting with `#`
+// `{NAME}` in pattern is replaced by `(definition of NAME)`, except inside [] or after `\`
+// the name of a rule is the last word of the line, so a pattern can contain spaces, but leading and trailing spaces are ignored
+// each rule is added to the token set in order, so rules with the same name become the patterns of one token
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpecErrorKind {
+  // a rule without name, or a definition without pattern
+  MissingName,
+  // a name which is not an identifier
+  InvalidName(String),
+  DuplicateDefinition(String),
+  UndefinedReference(String),
+  // the pattern (after expanding references) is invalid
+  Regex(ParseErrorKind),
+}
+
+// the error in spec at `line` and `col`, both start from 1, `col` counts bytes
+// if a regex error occurs inside an expanded reference, `col` points to the reference
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpecError {
+  pub line: usize,
+  pub col: usize,
+  pub kind: SpecErrorKind,
+}
+
+impl fmt::Display for SpecError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}:{}: ", self.line, self.col)?;
+    match &self.kind {
+      SpecErrorKind::MissingName => f.write_str("expect a name and a pattern"),
+      SpecErrorKind::InvalidName(name) => write!(f, "`{}` is not a valid name", name),
+      SpecErrorKind::DuplicateDefinition(name) => write!(f, "`{}` is already defined", name),
+      SpecErrorKind::UndefinedReference(name) => write!(f, "`{}` is not defined", name),
+      SpecErrorKind::Regex(kind) => write!(f, "{}", kind),
+    }
+  }
+}
+
+impl std::error::Error for SpecError {}
+
+fn is_ident(s: &[u8]) -> bool {
+  matches!(s, [b'a'..=b'z' | b'A'..=b'Z' | b'_', ref s @ ..] if s.iter().all(|&x| x.is_ascii_alphanumeric() || x == b'_'))
+}
+
+// expand the references in `pat`, return the expanded pattern and the column of each byte in it
+// `col` is the column of `pat[0]`
+fn expand(pat: &[u8], col: usize, defs: &HashMap<&str, Vec<u8>>) -> Result<(Vec<u8>, Vec<usize>), (usize, SpecErrorKind)> {
+  let (mut ret, mut cols) = (Vec::new(), Vec::new());
+  let (mut i, mut in_class) = (0, false);
+  while i < pat.len() {
+    let len = match pat[i] {
+      b'\\' => 2.min(pat.len() - i),
+      b']' if in_class => (in_class = false, 1).1,
+      // `[]` is an empty set in our syntax, so the `]` after `[` or `[^` closes it
+      b'[' if !in_class => (in_class = true, 1).1,
+      b'{' if !in_class && matches!(pat.get(i + 1), Some(b'a'..=b'z' | b'A'..=b'Z' | b'_')) => {
+        // if there is no valid name in {}, leave it to the regex parser to report it
+        match pat[i + 1..].iter().position(|&x| x == b'}') {
+          Some(end) if is_ident(&pat[i + 1..i + 1 + end]) => {
+            let name = std::str::from_utf8(&pat[i + 1..i + 1 + end]).unwrap();
+            let def = defs.get(name).ok_or_else(|| (col + i, SpecErrorKind::UndefinedReference(name.to_owned())))?;
+            ret.push(b'(');
+            ret.extend_from_slice(def);
+            ret.push(b')');
+            cols.resize(ret.len(), col + i);
+            i += end + 2;
+            continue;
+          }
+          _ => 1,
+        }
+      }
+      _ => 1,
+    };
+    for j in i..i + len { cols.push(col + j); }
+    ret.extend_from_slice(&pat[i..i + len]);
+    i += len;
+  }
+  Ok((ret, cols))
+}
+
+impl TokenSet {
+  pub fn from_spec(s: &str) -> Result<TokenSet, SpecError> {
+    let lines = s.lines().enumerate().map(|(idx, l)| (idx + 1, l));
+    let has_defs = s.lines().any(|l| l.trim() == "%%");
+    let mut defs = HashMap::default();
+    let mut ts = TokenSet::new();
+    let mut in_rules = !has_defs;
+    for (line, l) in lines {
+      let err = |col, kind| SpecError { line, col, kind };
+      let trimmed = l.trim();
+      if trimmed.is_empty() || trimmed.starts_with('#') { continue; }
+      if trimmed == "%%" && !in_rules {
+        in_rules = true;
+        continue;
+      }
+      // `start` is the column of the first non-blank char
+      let start = l.len() - l.trim_start().len() + 1;
+      let ws = |c: char| c.is_ascii_whitespace();
+      let (name, name_col, pat, pat_col) = match if in_rules { trimmed.rfind(ws) } else { trimmed.find(ws) } {
+        Some(sp) if in_rules => (&trimmed[sp + 1..], start + sp + 1, trimmed[..sp].trim_end(), start),
+        Some(sp) => {
+          let pat = trimmed[sp..].trim_start();
+          (&trimmed[..sp], start, pat, start + trimmed.len() - pat.len())
+        }
+        None => return Err(err(start, SpecErrorKind::MissingName)),
+      };
+      if !is_ident(name.as_bytes()) { return Err(err(name_col, SpecErrorKind::InvalidName(name.to_owned()))); }
+      let (pat, cols) = expand(pat.as_bytes(), pat_col, &defs).map_err(|(col, kind)| err(col, kind))?;
+      let re = parse(&pat).map_err(|e| err(cols.get(e.offset).copied().unwrap_or(pat_col + pat.len()), SpecErrorKind::Regex(e.kind)))?;
+      if in_rules {
+        ts.add_re(name, re);
+      } else if defs.insert(name, pat).is_some() {
+        return Err(err(name_col, SpecErrorKind::DuplicateDefinition(name.to_owned())));
+      }
+    }
+    Ok(ts)
+  }
+}
@@ -0,0 +1,100 @@
+use crate::*;
+
+#[derive(Debug, Clone)]
+pub struct TokenInfo {
+  // should be an identifier if the generated code is used
+  pub name: String,
+  // the lexer should drop a skipped token and continue, e.g., whitespaces and comments
+  pub skip: bool,
+  // if multiple tokens accept the longest match, the one with the highest priority wins
+  // if there are still multiple ones, the one whose pattern is registered first wins
+  pub priority: i32,
+}
+
+impl TokenInfo {
+  pub fn skip(&mut self, skip: bool) -> &mut TokenInfo { (self.skip = skip, self).1 }
+
+  pub fn priority(&mut self, priority: i32) -> &mut TokenInfo { (self.priority = priority, self).1 }
+}
+
+// a set of named tokens, each token can have several patterns, e.g.:
+// ```
+// let mut ts = TokenSet::new();
+// ts.add("IF", b"if")?;
+// ts.add("IDENT", br"[a-zA-Z_]\w*")?;
+// ts.add("SPACE", br"\s+")?.skip(true);
+// let dfa = ts.build();
+// ```
+// the accept ids in the dfa built from it are token ids, i.e., indices in `tokens`
+#[derive(Debug, Clone, Default)]
+pub struct TokenSet {
+  pub tokens: Vec<TokenInfo>,
+  // (token id, pattern) in the order of registration
+  pub patterns: Vec<(u32, Re)>,
+}
+
+impl TokenSet {
+  pub fn new() -> TokenSet { TokenSet::default() }
+
+  // return Err(e) if `re` is invalid, `e.idx` is the index of `re` in `patterns`
+  pub fn add(&mut self, name: &str, re: &[u8]) -> Result<&mut TokenInfo, ParseError> {
+    let re = parse(re).map_err(|e| ParseError { idx: self.patterns.len(), ..e })?;
+    Ok(self.add_re(name, re))
+  }
+
+  // if there is already a token named `name`, `re` becomes another pattern of it
+  pub fn add_re(&mut self, name: &str, re: Re) -> &mut TokenInfo {
+    let id = self.id(name).unwrap_or_else(|| {
+      self.tokens.push(TokenInfo { name: name.to_owned(), skip: false, priority: 0 });
+      self.tokens.len() as u32 - 1
+    });
+    self.patterns.push((id, re));
+    &mut self.tokens[id as usize]
+  }
+
+  pub fn id(&self, name: &str) -> Option<u32> { self.tokens.iter().position(|t| t.name == name).map(|id| id as u32) }
+
+  pub fn name(&self, id: u32) -> &str { &self.tokens[id as usize].name }
+
+  pub fn build(&self) -> Dfa {
+    let (nfa, order) = self.nfa();
+    let mut dfa = Dfa::from_nfa(&nfa);
+    self.map_acc(&mut dfa, &order);
+    dfa.minimize();
+    dfa
+  }
+
+  // like `build`, but also return the tokens that can never be the result of matching
+  pub fn build_with_warnings(&self) -> (Dfa, Vec<Shadowed>) {
+    let (nfa, order) = self.nfa();
+    let (mut dfa, sets) = Dfa::from_nfa_with_sets(&nfa);
+    self.map_acc(&mut dfa, &order);
+    let warnings = analysis::shadowed(&nfa, &dfa, &sets, self.tokens.len(), |id| self.patterns[order[id as usize]].0);
+    dfa.minimize();
+    (dfa, warnings)
+  }
+
+  // see `Overlap`, all the ids are token ids, overlaps between patterns of the same token are not reported
+  pub fn overlaps(&self) -> Vec<Overlap> {
+    let (nfa, order) = self.nfa();
+    let (mut dfa, sets) = Dfa::from_nfa_with_sets(&nfa);
+    self.map_acc(&mut dfa, &order);
+    analysis::overlaps(&nfa, &dfa, &sets, |id| self.patterns[order[id as usize]].0)
+  }
+
+  // the accept id `i` of the returned nfa is `patterns[order[i]]`
+  fn nfa(&self) -> (Nfa, Vec<usize>) {
+    // the nfa breaks ties by choosing the pattern with the smallest index, so sort the patterns by priority first
+    let mut order = (0..self.patterns.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&i| std::cmp::Reverse(self.tokens[self.patterns[i].0 as usize].priority));
+    let re = order.iter().map(|&i| self.patterns[i].1.clone()).collect::<Vec<_>>();
+    (Nfa::from_re1(&re), order)
+  }
+
+  // map the accept ids to token ids before minimizing, so states of different patterns of a token can be merged
+  fn map_acc(&self, dfa: &mut Dfa, order: &[usize]) {
+    for (acc, _) in &mut dfa.nodes {
+      if let Some(id) = acc { *id = self.patterns[order[*id as usize]].0; }
+    }
+  }
+}
@@ -0,0 +1,86 @@
+use re2dfa::*;
+
+fn shadowed(re: &[&[u8]]) -> Vec<Shadowed> { re2dfa_with_warnings(re.iter().copied()).unwrap().1 }
+
+fn example(s: &str, winner: u32) -> Option<(Vec<u8>, u32)> { Some((s.as_bytes().to_vec(), winner)) }
+
+#[test]
+fn shadowed_patterns() {
+  // `if` after `[a-z]+` is fully shadowed, `a&b` accepts nothing
+  let w = shadowed(&[b"[a-z]+", b"if", br"\d+", b"a&b"]);
+  assert_eq!(w, [Shadowed { id: 1, example: example("if", 0) }, Shadowed { id: 3, example: None }]);
+  assert_eq!(w[0].to_string(), r#"pattern 1 can never be matched, e.g., "if" is matched by pattern 0"#);
+  assert_eq!(w[1].to_string(), "pattern 3 can never be matched, because it accepts nothing");
+  // not shadowed: listed first, or partly shadowed, or only shadowed on strings which are not longest matches
+  assert!(shadowed(&[b"if", b"[a-z]+"]).is_empty());
+  assert!(shadowed(&[b"[a-z]+", b"ab|cd|12"]).is_empty());
+  assert!(shadowed(&[b"[a-z]", b"[a-z]+"]).is_empty());
+  // the example is the shortest string, the winner is the first pattern accepting it, which may not be the shortest one
+  let re: [&[u8]; 4] = [br"\w+", b"[a-c]+", b"abc|[a-c]{2}", b"[a-b]{3,}"];
+  let w = shadowed(&re);
+  assert_eq!(w.iter().map(|s| s.id).collect::<Vec<_>>(), [1, 2, 3]);
+  assert_eq!(w[1].example, example("aa", 0));
+  assert_eq!(w[2].example, example("aaa", 0));
+  // each example is accepted by the shadowed pattern, and the winner is the longest match on it
+  let dfa = re2dfa(re).unwrap();
+  for s in &w {
+    let (ex, winner) = s.example.clone().unwrap();
+    assert_eq!(dfa.longest_match(&ex), Some((winner, ex.len())));
+    assert_eq!(re2dfa([re[s.id as usize]]).unwrap().longest_match(&ex), Some((0, ex.len())));
+  }
+}
+
+#[test]
+fn shadowed_tokens() {
+  let mut ts = TokenSet::new();
+  ts.add("IDENT", b"[a-z]+").unwrap();
+  ts.add("IF", b"if").unwrap().priority(-1);
+  ts.add("INT", br"\d+").unwrap();
+  let (dfa, w) = ts.build_with_warnings();
+  assert_eq!(w, [Shadowed { id: 1, example: example("if", 0) }]);
+  assert_eq!(w[0].display_with(&ts).to_string(), r#"pattern IF can never be matched, e.g., "if" is matched by pattern IDENT"#);
+  assert!(dfa.equivalent(&ts.build()).is_ok());
+  // with a higher priority, `IF` wins regardless of the order
+  ts.tokens[1].priority(1);
+  assert!(ts.build_with_warnings().1.is_empty());
+  // a token is shadowed only if all its patterns are
+  ts.tokens[1].priority(-1);
+  ts.add("IF", b"0if").unwrap();
+  assert!(ts.build_with_warnings().1.is_empty());
+}
+
+fn overlap(ids: (u32, u32), example: &str, winner: u32) -> Overlap { Overlap { ids, example: example.as_bytes().to_vec(), winner } }
+
+#[test]
+fn overlap_patterns() {
+  let re: [&[u8]; 5] = [b"if", b"[a-z]+", b"[a-z0-9]+", br"\d+", b"x"];
+  let o = Nfa::from_re(re).unwrap().overlaps();
+  assert_eq!(o, [overlap((0, 1), "if", 0), overlap((0, 2), "if", 0), overlap((1, 2), "a", 1), overlap((1, 4), "x", 1),
+                 overlap((2, 3), "0", 2), overlap((2, 4), "x", 1)]);
+  assert_eq!(o[5].to_string(), r#"pattern 2 and 4 both accept "x", which is matched by pattern 1"#);
+  // each example is accepted by both patterns, and the winner is the longest match on it
+  let dfa = re2dfa(re).unwrap();
+  for o in &o {
+    for id in [o.ids.0, o.ids.1] { assert_eq!(re2dfa([re[id as usize]]).unwrap().longest_match(&o.example), Some((0, o.example.len()))); }
+    assert_eq!(dfa.longest_match(&o.example), Some((o.winner, o.example.len())));
+  }
+  // the example is the shortest common string, not the shortest string of either pattern
+  let o = Nfa::from_re([&b"a+b"[..], b"a(bc)*|aab", b"c"]).unwrap().overlaps();
+  assert_eq!(o, [overlap((0, 1), "aab", 0)]);
+  assert!(Nfa::from_re([&b"a"[..], b"b", b"~(.*)"]).unwrap().overlaps().is_empty());
+}
+
+#[test]
+fn overlap_tokens() {
+  // the winner follows the priority, and overlaps between patterns of the same token are not reported
+  let mut ts = TokenSet::new();
+  ts.add("IDENT", b"[a-z]+").unwrap();
+  ts.add("IF", b"if").unwrap().priority(1);
+  ts.add("IF", b"i[a-z]").unwrap();
+  ts.add("INT", br"\d+").unwrap();
+  let o = ts.overlaps();
+  assert_eq!(o, [overlap((0, 1), "ia", 1)]);
+  assert_eq!(o[0].display_with(&ts).to_string(), r#"pattern IDENT and IF both accept "ia", which is matched by pattern IF"#);
+  ts.tokens[1].priority(-1);
+  assert_eq!(ts.overlaps(), [overlap((0, 1), "ia", 0)]);
+}
@@ -0,0 +1,98 @@
+use std::convert::TryInto;
+use re2dfa::*;
+
+const RE: &[&[u8]] = &[b"if", b"else", br"[a-z_]\w*", br"\d+", br"\s+", b"==|="];
+const INPUT: &[u8] = b"if x == 1 else y = 22 iff elsee \x01 ab9";
+
+fn bytes() -> Vec<u8> { re2dfa(RE.iter().copied()).unwrap().to_bytes() }
+
+fn set(bytes: &mut [u8], idx: usize, x: u32) { bytes[idx..idx + 4].copy_from_slice(&x.to_le_bytes()); }
+
+fn get(bytes: &[u8], idx: usize) -> u32 { u32::from_le_bytes(bytes[idx..idx + 4].try_into().unwrap()) }
+
+// write the checksum of a forged buffer, which covers version, ec_num, state_num and the bytes after the header
+fn seal(bytes: &mut [u8]) {
+  let h = bytes[8..20].iter().chain(&bytes[24..]).fold(0x811c9dc5u32, |h, &b| (h ^ b as u32).wrapping_mul(0x01000193));
+  set(bytes, 20, h);
+}
+
+#[test]
+fn round_trip() {
+  let dfa = re2dfa(RE.iter().copied()).unwrap();
+  let bytes = dfa.to_bytes();
+  // any alignment works
+  let mut unaligned = vec![0];
+  unaligned.extend_from_slice(&bytes);
+  for bytes in [&bytes[..], &unaligned[1..]] {
+    let r = DfaRef::from_bytes(bytes).unwrap();
+    assert_eq!((r.ec_num, r.state_num), (dfa.ec_num, dfa.nodes.len()));
+    assert_eq!(r.tokens(INPUT).collect::<Vec<_>>(), dfa.tokens(INPUT).collect::<Vec<_>>());
+    for i in 0..INPUT.len() { assert_eq!(r.longest_match(&INPUT[i..]), dfa.longest_match(&INPUT[i..])); }
+    assert!(r.to_dfa().equivalent(&dfa).is_ok());
+    assert_eq!(r.to_dfa().to_bytes(), bytes);
+  }
+}
+
+#[test]
+fn invalid() {
+  let bytes = bytes();
+  for len in [0, 7, 23, 24, 24 + 256, bytes.len() - 1] {
+    let e = if len < 24 { BinaryError::BadMagic } else { BinaryError::BadLength };
+    assert_eq!(DfaRef::from_bytes(&bytes[..len]).err(), Some(e));
+  }
+  let mut longer = bytes.clone();
+  longer.push(0);
+  assert_eq!(DfaRef::from_bytes(&longer).err(), Some(BinaryError::BadLength));
+
+  let mut b = bytes.clone();
+  b[0] = b'R';
+  assert_eq!(DfaRef::from_bytes(&b).err(), Some(BinaryError::BadMagic));
+
+  let mut b = bytes.clone();
+  set(&mut b, 8, 2);
+  seal(&mut b);
+  assert_eq!(DfaRef::from_bytes(&b).err(), Some(BinaryError::UnsupportedVersion(2)));
+
+  // a flipped byte in ec, table and acc
+  for idx in [24, 24 + 256 + 1, bytes.len() - 1] {
+    let mut b = bytes.clone();
+    b[idx] ^= 1;
+    assert_eq!(DfaRef::from_bytes(&b).err(), Some(BinaryError::BadChecksum));
+  }
+  // a header with the same length but different sizes, which is also covered by the checksum
+  let (ec_num, state_num) = (get(&bytes, 12), get(&bytes, 16));
+  let mut b = bytes.clone();
+  set(&mut b, 12, 0);
+  set(&mut b, 16, state_num * (ec_num + 1));
+  assert_eq!(DfaRef::from_bytes(&b).err(), Some(BinaryError::BadChecksum));
+  seal(&mut b);
+  assert_eq!(DfaRef::from_bytes(&b).err(), Some(BinaryError::Malformed));
+}
+
+#[test]
+fn malformed() {
+  let bytes = bytes();
+  let (ec_num, state_num) = (get(&bytes, 12), get(&bytes, 16));
+  let table = 24 + 256;
+  let forge = |idx: usize, x: u32| {
+    let mut b = bytes.clone();
+    set(&mut b, idx, x);
+    seal(&mut b);
+    DfaRef::from_bytes(&b).err()
+  };
+  // an edge to a state that doesn't exist
+  assert_eq!(forge(table, state_num), Some(BinaryError::Malformed));
+  assert_eq!(forge(table + (state_num * ec_num - 1) as usize * 4, state_num + 1), Some(BinaryError::Malformed));
+  // an edge to the last state is fine
+  assert!(forge(table, state_num - 1).is_none());
+  // a class out of range
+  let mut b = bytes.clone();
+  b[24 + b'a' as usize] = ec_num as u8;
+  seal(&mut b);
+  assert_eq!(DfaRef::from_bytes(&b).err(), Some(BinaryError::Malformed));
+  // no state
+  let mut b = bytes[..table].to_vec();
+  set(&mut b, 16, 0);
+  seal(&mut b);
+  assert_eq!(DfaRef::from_bytes(&b).err(), Some(BinaryError::Malformed));
+}
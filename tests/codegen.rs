@@ -0,0 +1,190 @@
+// check that the generated code compiles and tokenizes in the same way as `Automaton::tokens`
+use re2dfa::*;
+use std::{io::Write, path::PathBuf, process::{Command, Stdio}};
+
+const PATTERNS: &[&[u8]] = &[b"if", b"else", br"[a-zA-Z_]\w*", br"\d+", br"\s+", br#""([^"\\]|\\.)*""#, b"==", b"=", br"/\*~(.*\*/.*)\*/"];
+const INPUT: &[u8] = b"if x1 == 23 else \"a\\\"b\" /* c */ iff = $elsewhere\n\"unclosed \xff";
+
+fn dir(name: &str) -> PathBuf {
+  let dir = std::env::temp_dir().join(format!("re2dfa_codegen_{}", name));
+  std::fs::create_dir_all(&dir).unwrap();
+  dir
+}
+
+// the output of the drivers below: "id len" for each token, and "err" for each byte that can't be matched
+fn expect(dfa: &Dfa, input: &[u8]) -> String {
+  dfa.tokens(input).map(|t| match t {
+    Ok((id, r)) => format!("{} {}\n", id, r.len()),
+    Err(_) => "err\n".to_owned(),
+  }).collect()
+}
+
+fn run(exe: &PathBuf, input: &[u8]) -> String {
+  let mut child = Command::new(exe).stdin(Stdio::piped()).stdout(Stdio::piped()).spawn().unwrap();
+  child.stdin.take().unwrap().write_all(input).unwrap();
+  let out = child.wait_with_output().unwrap();
+  assert!(out.status.success());
+  String::from_utf8(out.stdout).unwrap()
+}
+
+const C_MAIN: &str = r#"
+#include <stdio.h>
+#include "lexer.h"
+
+int main(void) {
+  static uint8_t buf[1 << 16];
+  size_t len = fread(buf, 1, sizeof buf, stdin), i = 0, token_len;
+  uint32_t id;
+  while (i < len) {
+    if (lexer_next_token(buf + i, len - i, &id, &token_len)) {
+      printf("%u %zu\n", (unsigned)id, token_len);
+      i += token_len;
+    } else {
+      printf("err\n");
+      i += 1;
+    }
+  }
+  return 0;
+}
+"#;
+
+// compile the generated code with `main`, return None if there is no C compiler
+fn compile_c(name: &str, dfa: &Dfa, tokens: Option<&TokenSet>, enc: Encoding, main: &str) -> Option<PathBuf> {
+  if Command::new("cc").arg("--version").stdout(Stdio::null()).status().is_err() {
+    eprintln!("cc not found, skip {}", name);
+    return None;
+  }
+  let dir = dir(name);
+  let (header, source) = dfa.gen_c("lexer", tokens, enc);
+  std::fs::write(dir.join("lexer.h"), header.to_string()).unwrap();
+  std::fs::write(dir.join("lexer.c"), source.to_string()).unwrap();
+  std::fs::write(dir.join("main.c"), main).unwrap();
+  let exe = dir.join("main");
+  let st = Command::new("cc").args(["-std=c99", "-Wall", "-Wextra", "-Werror", "-O2", "-o"]).arg(&exe)
+    .arg(dir.join("lexer.c")).arg(dir.join("main.c")).status().unwrap();
+  assert!(st.success());
+  Some(exe)
+}
+
+fn check_c(name: &str, dfa: &Dfa, tokens: Option<&TokenSet>, enc: Encoding) {
+  if let Some(exe) = compile_c(name, dfa, tokens, enc, C_MAIN) { assert_eq!(run(&exe, INPUT), expect(dfa, INPUT)); }
+}
+
+#[test]
+fn c_dense() {
+  check_c("c_dense", &re2dfa(PATTERNS.iter().copied()).unwrap(), None, Encoding::Dense);
+}
+
+#[test]
+fn c_comb() {
+  check_c("c_comb", &re2dfa(PATTERNS.iter().copied()).unwrap(), None, Encoding::Comb);
+}
+
+#[test]
+fn c_tokens() {
+  let mut tokens = TokenSet::new();
+  tokens.add("IF", b"if").unwrap();
+  tokens.add("IDENT", br"[a-z]\w*").unwrap();
+  tokens.add("INT", br"\d+").unwrap();
+  tokens.add("WS", br"\s+").unwrap().skip(true);
+  let dfa = tokens.build();
+  let (header, source) = dfa.gen_c("lexer", Some(&tokens), Encoding::Dense);
+  let (header, source) = (header.to_string(), source.to_string());
+  assert!(header.contains("#define LEXER_IDENT 1") && header.contains("#define LEXER_TOKEN_NUM 4"));
+  assert!(source.contains(r#"{"IF", "IDENT", "INT", "WS"}"#) && source.contains("{0, 0, 0, 1}"));
+  check_c("c_tokens", &dfa, Some(&tokens), Encoding::Comb);
+}
+
+const C_NAMES: &str = r#"
+#include <stdio.h>
+#include "lexer.h"
+
+int main(void) {
+  size_t i;
+  for (i = 0; i < LEXER_TOKEN_NUM; i++) printf("%s\n", lexer_names[i]);
+  return LEXER_IF != 0 || LEXER_x_1 != 5;
+}
+"#;
+
+#[test]
+fn c_names() {
+  // names that are not C identifiers, with chars that rust and C escape differently
+  let names = ["IF", "caf\u{e9}", "it's", "a\"b\\c", "what??=", "x_1", "tab\t"];
+  let mut tokens = TokenSet::new();
+  for (i, name) in names.iter().enumerate() { tokens.add(name, format!("{}", i).as_bytes()).unwrap(); }
+  let dfa = tokens.build();
+  let (header, source) = dfa.gen_c("lexer", Some(&tokens), Encoding::Comb);
+  let (header, source) = (header.to_string(), source.to_string());
+  assert!(header.contains("#define LEXER_x_1 5") && !header.contains("#define LEXER_it"));
+  assert!(source.contains(r#""caf\303\251", "it's", "a\"b\\c", "what\?\?=", "x_1", "tab\011""#));
+  if let Some(exe) = compile_c("c_names", &dfa, Some(&tokens), Encoding::Comb, C_NAMES) {
+    assert_eq!(run(&exe, b""), names.iter().map(|n| format!("{}\n", n)).collect::<String>());
+  }
+}
+
+#[test]
+fn c_direct() {
+  check_c("c_direct", &re2dfa(PATTERNS.iter().copied()).unwrap(), None, Encoding::Direct);
+}
+
+const RUST_MAIN: &str = r#"
+use std::io::Read;
+
+fn main() {
+  let mut buf = Vec::new();
+  std::io::stdin().read_to_end(&mut buf).unwrap();
+  let mut i = 0;
+  while i < buf.len() {
+    match lexer::next_token(&buf[i..]) {
+      Some((id, len)) => {
+        println!("{} {}", id, len);
+        i += len;
+      }
+      None => {
+        println!("err");
+        i += 1;
+      }
+    }
+  }
+}
+"#;
+
+// return the output of the generated code on `input`
+fn run_rust(name: &str, code: &str, input: &[u8]) -> String {
+  let dir = dir(name);
+  std::fs::write(dir.join("main.rs"), format!("mod lexer {{\n{}\n}}\n{}", code, RUST_MAIN)).unwrap();
+  let exe = dir.join("main");
+  let st = Command::new("rustc").args(["--edition", "2018", "-D", "warnings", "-O", "-o"]).arg(&exe).arg(dir.join("main.rs")).status().unwrap();
+  assert!(st.success());
+  run(&exe, input)
+}
+
+#[test]
+fn rust_encodings() {
+  let dfa = re2dfa(PATTERNS.iter().copied()).unwrap();
+  let expect = expect(&dfa, INPUT);
+  for (name, enc) in [("rust_dense", Encoding::Dense), ("rust_comb", Encoding::Comb), ("rust_direct", Encoding::Direct)] {
+    assert_eq!(run_rust(name, &dfa.gen_rust_encoded(None, enc).to_string(), INPUT), expect, "{:?}", enc);
+  }
+}
+
+#[test]
+fn rust_direct() {
+  // every byte, and a state whose edges cover all bytes
+  let dfa = re2dfa([&b"'"[..], br"\\", br"\x00+", br"a.*", br"[\x80-\xff]"]).unwrap();
+  let input = (0..=255u8).chain(b"a\\'\x00\x00b".iter().copied()).chain(b"\\\\''".iter().copied()).collect::<Vec<_>>();
+  let direct = run_rust("rust_direct_bytes", &dfa.gen_rust_encoded(None, Encoding::Direct).to_string(), &input);
+  assert_eq!(direct, expect(&dfa, &input));
+  assert_eq!(direct, run_rust("rust_default_bytes", &dfa.gen_rust().to_string(), &input));
+}
+
+#[test]
+fn rust_default() {
+  // the transition table is compressed by default
+  let dfa = re2dfa(PATTERNS.iter().copied()).unwrap();
+  let code = dfa.gen_rust().to_string();
+  assert_eq!(code, dfa.gen_rust_encoded(None, Encoding::Comb).to_string());
+  assert!(!code.contains("TABLE") && !code.contains("; EC_NUM]; STATE_NUM]"));
+  assert!(code.contains("pub static BASE: [") && code.contains("pub static CHECK: [") && code.contains("// comb table: "));
+  assert!(dfa.gen_rust_encoded(None, Encoding::Dense).to_string().contains("pub static TABLE: [[u8; EC_NUM]; STATE_NUM] = ["));
+}
@@ -0,0 +1,36 @@
+use re2dfa::*;
+
+// every transition of `comb` is the same as that of `dense`
+fn check(dfa: &Dfa) -> CombDfa {
+  let (dense, comb) = (DenseDfa::from_dfa(dfa), CombDfa::from_dfa(dfa));
+  for state in 0..dense.acc.len() as u32 {
+    for ch in 0..=255 { assert_eq!(comb.next(state, ch), dense.next(state, ch), "state {}, ch {}", state, ch); }
+  }
+  assert_eq!(comb.next.len(), comb.check.len());
+  assert!(comb.base.iter().all(|&b| b as usize + comb.ec_num <= comb.next.len()));
+  assert!(comb.default.iter().enumerate().all(|(s, &d)| d == DEAD || (d as usize) < s));
+  comb
+}
+
+#[test]
+fn small() {
+  for re in [&[&b"a"[..]][..], &[br"~(.*ab.*)"], &[br"(a|b)*a(a|b){6}"], &[b"if", br"\w+", br"\d+", br"\s+"]] {
+    check(&re2dfa(re.iter().copied()).unwrap());
+  }
+}
+
+#[test]
+fn keywords() {
+  // pseudo-random keywords, most rows only differ from the identifier state in one class
+  let mut x = 1u32;
+  let words = (0..300).map(|_| (0..3 + x % 6).map(|_| {
+    x = x.wrapping_mul(1103515245).wrapping_add(12345);
+    b'a' + (x >> 16) as u8 % 26
+  }).collect::<Vec<_>>()).collect::<Vec<_>>();
+  let re = words.iter().map(|w| &w[..]).chain([&br"[a-z_]\w*"[..], br"\d+", br"\s+"]).collect::<Vec<_>>();
+  let dfa = re2dfa(re.iter().copied()).unwrap();
+  let comb = check(&dfa);
+  assert!(comb.ratio() < 0.25, "{}", comb.ratio());
+  let input = words.iter().flat_map(|w| w.iter().copied().chain(*b" x1 ")).collect::<Vec<_>>();
+  assert_eq!(comb.tokens(&input).collect::<Vec<_>>(), dfa.tokens(&input).collect::<Vec<_>>());
+}
@@ -0,0 +1,124 @@
+// all constructions give dfas equivalent to the one from `re2dfa`, i.e., Thompson nfa and subset construction
+use re2dfa::*;
+
+const PATTERN_SETS: &[&[&[u8]]] = &[
+  &[b"if", b"else", br"[a-z_]\w*", br"\d+", br"\d+\.\d*", br"\s+", b"==|="],
+  &[br"(a|b)*abb", br"a*b*", br"(ab|ba)*", br"b"],
+  &[br"/\*~(.*\*/.*)\*/", br"/", br"\*"],
+  &[br"\w+&~(\d+)", br"\d+", br"~(a*)&~(b*)&[ab]{0,3}"],
+  &[br"~(~(a|b)*|c)", br"(a&b)|c*", br"~(a*)b&(.*b)", br"x(~y|z*)*"],
+  &[br"a?", br"b*", br"(c?)*"],
+  &[br"(a*|b)*c", br"((a*)*|b*)*d", br"(a?b?)*e"],
+];
+
+fn expect(re: &[&[u8]]) -> Dfa { re2dfa(re.iter().copied()).unwrap() }
+
+#[test]
+fn derivative() {
+  for re in PATTERN_SETS {
+    let mut dfa = Dfa::from_re(&parse_all(re.iter().copied()).unwrap());
+    dfa.minimize();
+    let expect = expect(re);
+    assert!(dfa.equivalent(&expect).is_ok(), "{:?}", dfa.equivalent(&expect).err().map(|c| c.to_string()));
+    assert_eq!(dfa.nodes.len(), expect.nodes.len());
+    let opt = Options { construction: Construction::Derivative, ..Default::default() };
+    assert!(re2dfa_with(re.iter().copied(), &opt).unwrap().equivalent(&expect).is_ok());
+  }
+}
+
+fn with(construction: Construction, re: &[&[u8]]) -> Dfa {
+  re2dfa_with(re.iter().copied(), &Options { construction, ..Default::default() }).unwrap()
+}
+
+#[test]
+fn glushkov() {
+  for re in PATTERN_SETS {
+    let (dfa, expect) = (with(Construction::Glushkov, re), with(Construction::Thompson, re));
+    assert!(dfa.equivalent(&expect).is_ok(), "{:?}", dfa.equivalent(&expect).err().map(|c| c.to_string()));
+    assert_eq!(dfa.nodes.len(), expect.nodes.len());
+  }
+}
+
+#[test]
+fn glushkov_nullable() {
+  // only the first pattern that accepts the empty string is accepted by the start node
+  let re: &[&[u8]] = &[b"a", b"b*", br"c?", br"~(d)"];
+  let nfa = Nfa::glushkov(&parse_all(re.iter().copied()).unwrap());
+  assert_eq!(nfa.nodes[0].id, Some(1));
+  assert!(nfa.nodes[0].eps_edges.is_empty() && nfa.nodes.iter().all(|n| n.eps_edges.is_empty()));
+  let dfa = with(Construction::Glushkov, re);
+  assert_eq!(dfa.nodes[0].0, Some(1));
+  assert!(dfa.equivalent(&expect(re)).is_ok());
+}
+
+#[test]
+fn glushkov_dfa_frag() {
+  // the dfas compiled for `~` and `&` are embedded under `*` and in concatenation
+  let re: &[&[u8]] = &[br"(~(a*)b)*", br"x~(ab)y", br"(a&.)*c", br"(x~(y*)&x.*)+z"];
+  let dfa = with(Construction::Glushkov, re);
+  assert!(dfa.equivalent(&expect(re)).is_ok());
+  for (input, m) in [(&b"bab"[..], Some((0, 3))), (b"xaby", Some((0, 3))), (b"xy", Some((1, 2))), (b"aaac", Some((2, 4))),
+    (b"xaz", Some((3, 3))), (b"xyz", Some((1, 2))), (b"ab", None)] {
+    assert_eq!(dfa.longest_match(input), m, "{}", String::from_utf8_lossy(input));
+  }
+  let re: &[&[u8]] = &[br"~(a)~(b)", br"(~(ab)&\w*)*"];
+  assert!(with(Construction::Glushkov, re).equivalent(&expect(re)).is_ok());
+}
+
+#[test]
+fn glushkov_first_pattern() {
+  // when several patterns accept the same string, the one with the smallest index is chosen
+  let re: &[&[u8]] = &[b"if", br"[a-z]+", br"i.", b"iff"];
+  let dfa = with(Construction::Glushkov, re);
+  assert!(dfa.equivalent(&expect(re)).is_ok());
+  assert_eq!(dfa.longest_match(b"if"), Some((0, 2)));
+  assert_eq!(dfa.longest_match(b"iff"), Some((1, 3)));
+  assert_eq!(dfa.longest_match(b"i1"), Some((2, 2)));
+}
+
+// the eps closure of each node by a plain search, `close[x][y]` iff y is reachable from x via eps edges
+fn naive_e_close(nfa: &Nfa) -> Vec<Vec<bool>> {
+  (0..nfa.nodes.len()).map(|x| {
+    let (mut close, mut stack) = (vec![false; nfa.nodes.len()], vec![x]);
+    close[x] = true;
+    while let Some(x) = stack.pop() {
+      for &y in &nfa.nodes[x].eps_edges {
+        if !close[y as usize] { close[y as usize] = true; stack.push(y as usize); }
+      }
+    }
+    close
+  }).collect()
+}
+
+#[test]
+fn e_close() {
+  // nested `*` and `?` give eps cycles in the Thompson nfa
+  let cycles: &[&[&[u8]]] = &[&[br"((a*)*)*", br"(a?b?)*c", br"((a|b*)*|c?)*d", br"(((a?)*b*)*|(c*)*)*e"]];
+  let mut has_cycle = false;
+  for re in PATTERN_SETS.iter().chain(cycles) {
+    let re = parse_all(re.iter().copied()).unwrap();
+    let mut nfa = Nfa::thompson(&re);
+    let lazy = Dfa::from_nfa(&nfa);
+    let (n, expect) = (nfa.nodes.len(), naive_e_close(&nfa));
+    let elem_len = tools::bitset::bslen(n);
+    let mut stack = Vec::new();
+    for (x, close) in expect.iter().enumerate() {
+      let mut set = vec![0; elem_len];
+      nfa.add_e_close(&mut set, x as u32, &mut stack);
+      assert!((0..n).all(|y| (set[y / 32] >> (y % 32) & 1 != 0) == close[y]));
+      has_cycle |= (0..x).any(|y| close[y] && expect[y][x]);
+    }
+    nfa.compute_e_close();
+    for (row, close) in nfa.e_close.chunks(elem_len).zip(&expect) {
+      assert!((0..n).all(|y| (row[y / 32] >> (y % 32) & 1 != 0) == close[y]));
+    }
+    assert!(Dfa::from_nfa(&nfa).equivalent(&lazy).is_ok());
+    assert!(Dfa::from_nfa(&Nfa::from_re1(&re)).equivalent(&lazy).is_ok());
+  }
+  assert!(has_cycle);
+  for re in PATTERN_SETS.iter().chain(cycles) {
+    let (lazy, eager) = (Options { lazy_e_close: true, ..Default::default() }, Options::default());
+    let lazy = re2dfa_with(re.iter().copied(), &lazy).unwrap();
+    assert!(lazy.equivalent(&re2dfa_with(re.iter().copied(), &eager).unwrap()).is_ok());
+  }
+}
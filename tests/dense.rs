@@ -0,0 +1,47 @@
+use re2dfa::*;
+
+// `dense` has the same transitions and accept ids as `dfa`, and matches in the same way
+fn check(dfa: &Dfa, inputs: &[&[u8]]) {
+  let dense = DenseDfa::from_dfa(dfa);
+  assert_eq!(dense.table.len(), dfa.nodes.len() * dfa.ec_num);
+  for (state, (acc, edges)) in dfa.nodes.iter().enumerate() {
+    let state = state as u32;
+    assert_eq!(dense.acc[state as usize], *acc);
+    for ch in 0..=255u8 {
+      let next = edges.get(&dfa.ec[ch as usize]).copied();
+      assert_eq!(dense.next(state, ch), next.unwrap_or(DEAD));
+      assert_eq!(Automaton::next(&dense, state, ch), next);
+    }
+  }
+  for input in inputs {
+    assert_eq!(dense.longest_match(input), dfa.longest_match(input));
+    assert!(dense.tokens(input).eq(dfa.tokens(input)));
+  }
+}
+
+#[test]
+fn agree() {
+  let inputs: [&[u8]; 6] = [b"", b"if x1 == 23 else", b"\xff\x00", b"ifelse", b"/* a */ b", b"1e5 0x1f"];
+  let dfa = re2dfa([&b"if"[..], b"else", br"[a-zA-Z_]\w*", br"\d+", br"\s+", b"==", br"/\*~(.*\*/.*)\*/"]).unwrap();
+  // the minimized dfa has no dead state, so the missing edges become `DEAD`
+  assert!(dfa.nodes.iter().any(|(_, edges)| edges.len() < dfa.ec_num));
+  check(&dfa, &inputs);
+  // the dead state of an unminimized dfa is kept as a normal state, but matching gives the same result
+  let raw = Dfa::from_nfa(&Nfa::from_re([&b"ab"[..], b"a.c"]).unwrap());
+  assert!(raw.nodes.iter().all(|(_, edges)| edges.len() == raw.ec_num));
+  check(&raw, &inputs);
+  check(&raw, &[b"ab", b"abc", b"axcab", b"aa"]);
+  // no accepting state, and nothing can be matched, including the empty input
+  for re in [&b"a&b"[..], b"~(.*)", br"\d&[a-z]+"] {
+    let dfa = re2dfa([re]).unwrap();
+    assert!(dfa.nodes.iter().all(|(acc, _)| acc.is_none()));
+    check(&dfa, &inputs);
+    let dense = DenseDfa::from_dfa(&dfa);
+    assert!(dense.acc.iter().all(|acc| acc.is_none()));
+    assert!(inputs.iter().all(|i| dense.longest_match(i).is_none()));
+  }
+  // the empty input and an empty pattern give no match, and no token
+  let dense = DenseDfa::from_dfa(&re2dfa([&b"a*"[..]]).unwrap());
+  assert_eq!((dense.longest_match(b""), dense.longest_match(b"b"), dense.longest_match(b"aab")), (None, None, Some((0, 2))));
+  assert_eq!(dense.tokens(b"").count(), 0);
+}
@@ -0,0 +1,43 @@
+use re2dfa::*;
+
+// `LazyDfa` gives the same results as the eager dfa, with or without the eps closure matrix,
+// and with a cache so small that it is flushed in the middle of matching
+fn check(re: &[&[u8]], inputs: &[&[u8]]) {
+  let nfa = Nfa::from_re(re.iter().copied()).unwrap();
+  let eager = Dfa::from_nfa(&nfa);
+  // `e_close` is empty, so the closures are computed on demand
+  let lazy_nfa = Nfa::thompson(&parse_all(re.iter().copied()).unwrap());
+  for cache_size in [2, 3, 1000] {
+    for nfa in [&nfa, &lazy_nfa] {
+      let lazy = LazyDfa::new(nfa, cache_size);
+      for input in inputs {
+        for i in 0..input.len() { assert_eq!(lazy.longest_match(&input[i..]), eager.longest_match(&input[i..])); }
+        assert_eq!(lazy.tokens(input).collect::<Vec<_>>(), eager.tokens(input).collect::<Vec<_>>());
+      }
+      assert!(lazy.cached() <= cache_size);
+      if cache_size <= 3 { assert!(lazy.flushes() > 0); } else { assert_eq!(lazy.flushes(), 0); }
+    }
+  }
+}
+
+#[test]
+fn same_as_eager() {
+  check(&[b"if", b"else", br"[a-z_]\w*", br"\d+", br"\s+", b"==|="], &[b"if x == 1 else y = 22 iff elsee", b"", b"  \x01 ab9"]);
+  check(&[br"(a|b)*a(a|b){5}", b"b+"], &[b"abababbbabaabbbaaababbbaaa", b"bbbbbabbbbb"]);
+  check(&[br"/\*~(.*\*/.*)\*/", b"/", br"\*"], &[b"/* x */ */ /**/*"]);
+}
+
+#[test]
+fn flush_keeps_start() {
+  // each step needs a new state, so a cache of 2 is flushed at every step after the first
+  let nfa = Nfa::from_re([&br"(a|b)*a(a|b){3}"[..]]).unwrap();
+  let eager = Dfa::from_nfa(&nfa);
+  let lazy = LazyDfa::new(&nfa, 2);
+  let input = b"abbbaabab";
+  assert_eq!(lazy.longest_match(input), eager.longest_match(input));
+  assert_eq!(lazy.longest_match(input), Some((0, 9)));
+  assert!(lazy.flushes() >= input.len() - 2);
+  assert!(lazy.cached() <= 2);
+  // matching again after the flushes starts from the same start state
+  assert_eq!(lazy.longest_match(&input[1..]), eager.longest_match(&input[1..]));
+}
@@ -0,0 +1,47 @@
+use re2dfa::*;
+
+type Token = Result<(u32, std::ops::Range<usize>), usize>;
+
+fn lex(re: &[&[u8]], input: &[u8]) -> Vec<Token> { re2dfa(re.iter().copied()).unwrap().tokens(input).collect() }
+
+#[test]
+fn error() {
+  // each byte that starts no token is an error at its position, and lexing continues from the next byte
+  assert_eq!(lex(&[b"[a-z]+", br"\d+", b" "], b"ab$1 %%x\xff"),
+             [Ok((0, 0..2)), Err(2), Ok((1, 3..4)), Ok((2, 4..5)), Err(5), Err(6), Ok((0, 7..8)), Err(8)]);
+  // a failed longest match backtracks to the last accepting state, or gives an error at its start
+  assert_eq!(lex(&[b"abc", b"a", b"b"], b"ababcabd"),
+             [Ok((1, 0..1)), Ok((2, 1..2)), Ok((0, 2..5)), Ok((1, 5..6)), Ok((2, 6..7)), Err(7)]);
+  assert_eq!(lex(&[b"abc", b"b"], b"abd"), [Err(0), Ok((1, 1..2)), Err(2)]);
+  // nothing can be matched
+  assert_eq!(lex(&[b"a&b"], b"ab"), [Err(0), Err(1)]);
+}
+
+#[test]
+fn empty() {
+  assert!(lex(&[b"a"], b"").is_empty());
+  // an empty match is not a token
+  assert_eq!(lex(&[b"a*", b"b?"], b"cab"), [Err(0), Ok((0, 1..2)), Ok((1, 2..3))]);
+  // the lexer stays at the end of input
+  let dfa = re2dfa([&b"a"[..]]).unwrap();
+  let mut lexer = Lexer::new(&dfa, b"a");
+  assert_eq!((lexer.next(), lexer.next(), lexer.next(), lexer.pos), (Some(Ok((0, 0..1))), None, None, 1));
+  // `pos` can be set to continue from another position
+  lexer.pos = 0;
+  assert_eq!(lexer.next(), Some(Ok((0, 0..1))));
+}
+
+#[test]
+fn skip() {
+  // the lexer yields skipped tokens too, the caller drops them by the skip flags of `TokenSet`
+  let mut ts = TokenSet::new();
+  ts.add("IDENT", b"[a-z]+").unwrap();
+  ts.add("SPACE", br"\s+").unwrap().skip(true);
+  ts.add("COMMENT", br"#[^\n]*").unwrap().skip(true);
+  let dfa = ts.build();
+  let input = b"a  b # c\n\t$d";
+  let all = dfa.tokens(input).collect::<Vec<_>>();
+  assert_eq!(all, [Ok((0, 0..1)), Ok((1, 1..3)), Ok((0, 3..4)), Ok((1, 4..5)), Ok((2, 5..8)), Ok((1, 8..10)), Err(10), Ok((0, 11..12))]);
+  let kept = all.into_iter().filter(|t| !matches!(t, Ok((id, _)) if ts.tokens[*id as usize].skip)).collect::<Vec<_>>();
+  assert_eq!(kept, [Ok((0, 0..1)), Ok((0, 3..4)), Err(10), Ok((0, 11..12))]);
+}
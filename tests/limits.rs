@@ -0,0 +1,63 @@
+use re2dfa::*;
+
+fn opt(construction: Construction, limits: Limits) -> Options { Options { construction, limits, ..Default::default() } }
+
+const CONSTRUCTIONS: [Construction; 3] = [Construction::Thompson, Construction::Glushkov, Construction::Derivative];
+
+#[test]
+fn blow_up() {
+  let limits = Limits { dfa_states: 1000, ..Default::default() };
+  for c in CONSTRUCTIONS {
+    let e = re2dfa_with([&b"if"[..], br"\w+", br"(a|b)*a(a|b){12}"], &opt(c, limits)).err();
+    assert_eq!(e, Some(BuildError::Limit(LimitError { kind: LimitKind::DfaStates, patterns: vec![2] })), "{:?}", c);
+  }
+}
+
+#[test]
+fn blow_up_in_complement() {
+  // the dfas compiled for `~` and `&` are limited too, and the error names the pattern containing them
+  let limits = Limits { dfa_states: 1000, ..Default::default() };
+  for c in CONSTRUCTIONS {
+    for re in [&br"~((a|b)*a(a|b){12})"[..], br"x(\w*&~((a|b)*a(a|b){12}))", br"~(~((a|b)*a(a|b){12})c)"] {
+      let e = re2dfa_with([&b"if"[..], br"\w+", re], &opt(c, limits)).err();
+      assert_eq!(e, Some(BuildError::Limit(LimitError { kind: LimitKind::DfaStates, patterns: vec![2] })), "{:?}", c);
+    }
+  }
+  let limits = Limits { memory: 1 << 16, ..Default::default() };
+  for c in CONSTRUCTIONS {
+    let e = re2dfa_with([&br"~((a|b)*a(a|b){12})"[..]], &opt(c, limits)).err();
+    assert_eq!(e, Some(BuildError::Limit(LimitError { kind: LimitKind::Memory, patterns: vec![0] })), "{:?}", c);
+  }
+}
+
+#[test]
+fn within_limits() {
+  let limits = Limits { nfa_nodes: 1000, dfa_states: 1000, memory: 1 << 20 };
+  let re = [&b"if"[..], br"\w+", br"/\*~(.*\*/.*)\*/", br"\d+&~(0\d+)"];
+  let expect = re2dfa(re).unwrap();
+  for c in CONSTRUCTIONS {
+    assert!(re2dfa_with(re, &opt(c, limits)).unwrap().equivalent(&expect).is_ok(), "{:?}", c);
+  }
+}
+
+#[test]
+fn nfa_nodes() {
+  // each pattern alone has about 10000 nfa nodes, the construction stops within the first one,
+  // so the second one is never built and not blamed, though it is as large as the first one
+  let big = br"(a{99}){99}";
+  let limits = Limits { nfa_nodes: 1000, ..Default::default() };
+  let re = parse_all([&b"if"[..], big, big]).unwrap();
+  for c in [Construction::Thompson, Construction::Glushkov] {
+    let e = re2dfa_with([&b"if"[..], big, big], &opt(c, limits)).err();
+    assert_eq!(e, Some(BuildError::Limit(LimitError { kind: LimitKind::NfaNodes, patterns: vec![1] })), "{:?}", c);
+  }
+  let e = LimitError { kind: LimitKind::NfaNodes, patterns: vec![1] };
+  assert_eq!(Nfa::thompson_limited(&re, &limits).err(), Some(e.clone()));
+  assert_eq!(Nfa::glushkov_limited(&re, &limits).err(), Some(e));
+  // no pattern is too large alone, the ones with the most nodes are blamed
+  let limits = Limits { nfa_nodes: 100, ..Default::default() };
+  let re = parse_all([&b"a{40}"[..], b"b", b"c{40}", b"d{40}"]).unwrap();
+  let e = Nfa::thompson_limited(&re, &limits).err().unwrap();
+  assert_eq!((e.kind, e.patterns), (LimitKind::NfaNodes, vec![0, 2]));
+  assert!(Nfa::thompson_limited(&re[..3], &limits).is_ok());
+}
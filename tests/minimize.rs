@@ -0,0 +1,105 @@
+use re2dfa::*;
+
+fn unminimized(re: &[&[u8]]) -> Dfa { Dfa::from_nfa(&Nfa::from_re(re.iter().copied()).unwrap()) }
+
+fn minimized(re: &[&[u8]]) -> Dfa {
+  let mut dfa = unminimized(re);
+  dfa.minimize();
+  dfa
+}
+
+// a dfa on "a" (class 0) and "b" (class 1), `nodes[i] = (acc, next on a, next on b)`
+fn hand(nodes: &[(Option<u32>, Option<u32>, Option<u32>)]) -> Dfa {
+  let mut ec = [2; 256];
+  ec[b'a' as usize] = 0;
+  ec[b'b' as usize] = 1;
+  let nodes = nodes.iter().map(|&(acc, a, b)| {
+    let mut link = tools::HashMap::default();
+    if let Some(a) = a { link.insert(0, a); }
+    if let Some(b) = b { link.insert(1, b); }
+    (acc, link)
+  }).collect();
+  Dfa { nodes, ec_num: 3, ec }
+}
+
+#[test]
+fn state_num() {
+  for (re, n) in [
+    (&[&br"(a|b)*abb"[..]][..], 4),
+    (&[br"a*"], 1),
+    (&[br"(0|1(01*0)*1)*"], 3),
+    (&[br"(a|b)*a(a|b){3}"], 16),
+    (&[b"if", br"[a-z]+"], 4),
+    (&[b"a", b"b", b"ab|ba"], 4),
+    (&[br"0?(10)*1?"], 3),
+  ] {
+    let dfa = minimized(re);
+    assert_eq!(dfa.nodes.len(), n, "{:?}", re.iter().map(|r| String::from_utf8_lossy(r)).collect::<Vec<_>>());
+    // minimizing again changes nothing
+    let mut dfa1 = minimized(re);
+    dfa1.minimize();
+    assert_eq!(dfa1.nodes.len(), n);
+  }
+}
+
+#[test]
+fn language() {
+  for re in [
+    &[&br"(a|b)*abb"[..]][..],
+    &[b"if", b"else", br"[a-z_]\w*", br"\d+", br"\d+\.\d*", br"\s+"],
+    &[b"a", b"b", b"ab|ba", b"[ab]+"],
+    &[br"/\*~(.*\*/.*)\*/", br"\w+&~(\d+)"],
+  ] {
+    let (dfa, min) = (unminimized(re), minimized(re));
+    assert!(min.nodes.len() <= dfa.nodes.len());
+    assert!(min.equivalent(&dfa).is_ok());
+    // the accept ids are kept, not only renamed
+    let input = b"if else x1 12.5 abba /* a */ ba ab b a";
+    assert_eq!(min.tokens(input).collect::<Vec<_>>(), dfa.tokens(input).collect::<Vec<_>>());
+  }
+}
+
+#[test]
+fn accept_ids() {
+  // the states after "a" and "b" differ only in accept ids, while the states after "ab" and "ba" are merged
+  let dfa = minimized(&[b"a", b"b", b"ab|ba"]);
+  let mut ids = dfa.nodes.iter().filter_map(|&(acc, _)| acc).collect::<Vec<_>>();
+  ids.sort_unstable();
+  assert_eq!(ids, [0, 1, 2]);
+  assert_eq!(dfa.longest_match(b"a"), Some((0, 1)));
+  assert_eq!(dfa.longest_match(b"b"), Some((1, 1)));
+  assert_eq!(dfa.longest_match(b"ba"), Some((2, 2)));
+}
+
+#[test]
+fn empty_language() {
+  // the start state is in the same block as the dead state
+  let dfa = minimized(&[br"~(.*)", br"a&b"]);
+  assert_eq!(dfa.nodes.len(), 1);
+  assert!(dfa.nodes[0].0.is_none() && dfa.nodes[0].1.is_empty());
+  // the accepting state is unreachable
+  let mut dfa = hand(&[(None, Some(1), None), (None, Some(0), Some(1)), (Some(0), None, None)]);
+  dfa.minimize();
+  assert_eq!(dfa.nodes.len(), 1);
+  assert!(dfa.nodes[0].0.is_none() && dfa.nodes[0].1.is_empty());
+}
+
+#[test]
+fn start_state() {
+  // state 2 is equivalent to the start state 0, and state 1 is equivalent to state 3
+  let mut dfa = hand(&[(None, Some(3), None), (Some(0), Some(2), Some(4)), (None, Some(1), None), (Some(0), Some(0), Some(4)), (None, None, None)]);
+  dfa.minimize();
+  assert_eq!(dfa.nodes.len(), 2);
+  let (acc0, link0) = &dfa.nodes[0];
+  assert_eq!((*acc0, link0.len()), (None, 1));
+  let next = link0[&0];
+  assert_eq!(dfa.nodes[next as usize].0, Some(0));
+  assert_eq!(dfa.nodes[next as usize].1.get(&0), Some(&0));
+  assert_eq!(dfa.nodes[next as usize].1.get(&1), None);
+  // the start state is accepting and equivalent to a later state
+  let mut dfa = hand(&[(Some(1), Some(1), None), (None, Some(2), None), (Some(1), Some(1), None)]);
+  dfa.minimize();
+  assert_eq!(dfa.nodes.len(), 2);
+  assert_eq!(dfa.nodes[0].0, Some(1));
+  assert_eq!(dfa.longest_match(b"aaaaa"), Some((1, 4)));
+}
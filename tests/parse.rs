@@ -0,0 +1,139 @@
+use re2dfa::*;
+
+// (offset, kind) of the syntax error in `re`
+fn err(re: &[u8]) -> (usize, ParseErrorKind) {
+  let e = parse(re).err().unwrap_or_else(|| panic!("{} should be invalid", String::from_utf8_lossy(re)));
+  (e.offset, e.kind)
+}
+
+// whether `re` accepts the whole `input`
+fn accept(re: &[u8], input: &[u8]) -> bool {
+  let dfa = re2dfa([re]).unwrap();
+  input.iter().try_fold(0, |state, &ch| dfa.nodes[state as usize].1.get(&dfa.ec[ch as usize]).copied())
+    .is_some_and(|state| dfa.nodes[state as usize].0.is_some())
+}
+
+#[test]
+fn repeat() {
+  for (re, yes, no) in [
+    (&b"a{3}"[..], &[&b"aaa"[..]][..], &[&b""[..], b"aa", b"aaaa"][..]),
+    (b"a{2,}", &[b"aa", b"aaa", b"aaaaaaaa"], &[b"", b"a"]),
+    (b"a{1,3}", &[b"a", b"aa", b"aaa"], &[b"", b"aaaa"]),
+    (b"a{0}b", &[b"b"], &[b"", b"ab"]),
+    (b"a{0,}", &[b"", b"a", b"aaa"], &[b"b"]),
+    (b"(ab){1,2}c", &[b"abc", b"ababc"], &[b"c", b"abababc"]),
+    (b"[ab]{2}", &[b"ab", b"ba", b"bb"], &[b"a", b"abb"]),
+    (br"a\{2\}", &[b"a{2}"], &[b"aa"]),
+  ] {
+    for &input in yes { assert!(accept(re, input), "{:?}", (String::from_utf8_lossy(re), String::from_utf8_lossy(input))); }
+    for &input in no { assert!(!accept(re, input), "{:?}", (String::from_utf8_lossy(re), String::from_utf8_lossy(input))); }
+  }
+  assert!(accept(b"a{1000}", &[b'a'; 1000]) && !accept(b"a{1000}", &[b'a'; 999]));
+}
+
+#[test]
+fn invalid_repeat() {
+  use ParseErrorKind::*;
+  for re in [&b"a{3,2}"[..], b"a{1001}", b"a{", b"a{,3}", b"a{}", b"a{3", b"a{3,", b"a{3,x}", b"a{x}", b"a{99999999999999999999}"] {
+    assert_eq!(err(re), (1, InvalidRepeat), "{}", String::from_utf8_lossy(re));
+  }
+  assert_eq!(err(b"{3}"), (0, DanglingQuantifier));
+  assert_eq!(err(b"a{2}{3}"), (4, DanglingQuantifier));
+  assert_eq!(err(b"a*{3}"), (2, DanglingQuantifier));
+  // the expansion would exceed `MAX_EXPAND`, the offset points to the `{` that exceeds it
+  assert_eq!(err(b"(a{1000}){1000}"), (9, InvalidRepeat));
+  assert_eq!(err(b"((a{100}){100}){100}"), (15, InvalidRepeat));
+  assert!(parse(b"(a{100}){100}").is_ok());
+}
+
+#[test]
+fn error_kind() {
+  use ParseErrorKind::*;
+  for (re, offset, kind) in [
+    (&br"(a"[..], 0, UnclosedGroup), (br"a(b|c", 1, UnclosedGroup), (br"(a(b)", 0, UnclosedGroup), (br"((a)", 0, UnclosedGroup),
+    (br"[ab", 0, UnclosedClass), (br"a[^b", 1, UnclosedClass), (br"[a-", 0, UnclosedClass), (br"([a)", 1, UnclosedClass),
+    (br"ab)", 2, UnmatchedParen), (br"(a))", 3, UnmatchedParen), (br")", 0, UnmatchedParen),
+    (br"*a", 0, DanglingQuantifier), (br"a**", 2, DanglingQuantifier), (br"a+?", 2, DanglingQuantifier),
+    (br"(?a)", 1, DanglingQuantifier), (br"(a|*)", 3, DanglingQuantifier), (br"(a|{3})", 3, DanglingQuantifier),
+    (br"|*", 1, DanglingQuantifier),
+    (br"a||b", 1, EmptyAlternative), (br"|a", 0, EmptyAlternative), (br"a|", 1, EmptyAlternative), (br"(a|)", 2, EmptyAlternative),
+    (br"(|a)", 1, EmptyAlternative), (br"|", 0, EmptyAlternative),
+    (br"\xg0", 0, InvalidHexEscape), (br"a\x1", 1, InvalidHexEscape), (br"[\xzz]", 1, InvalidHexEscape),
+    (br"\q", 0, InvalidEscape), (br"a\q", 1, InvalidEscape), (br"[a\q]", 2, InvalidEscape), (br"[\", 1, InvalidEscape),
+    (br"a]", 1, UnexpectedChar), (br"a}", 1, UnexpectedChar), (br"[a[b]", 2, UnexpectedChar), (br"^a", 0, UnexpectedChar),
+  ] {
+    assert_eq!(err(re), (offset, kind), "{}", String::from_utf8_lossy(re));
+  }
+}
+
+#[test]
+fn error_idx() {
+  let e = re2dfa([&b"a"[..], b"a(b", b"("]).err().unwrap();
+  assert_eq!(e, ParseError { idx: 1, offset: 1, kind: ParseErrorKind::UnclosedGroup });
+  assert_eq!(e.to_string(), "unclosed group at byte 1 of pattern 1");
+  let e = parse_all([&b"[a-z]+"[..], b"x", br"\d**"]).err().unwrap();
+  assert_eq!(e.to_string(), "dangling quantifier at byte 3 of pattern 2");
+  assert_eq!(parse(b"a(b").err().map(|e| e.idx), Some(0));
+}
+
+#[test]
+fn shorthand() {
+  for (re, f) in [
+    (&br"\d"[..], &(|x: u8| x.is_ascii_digit()) as &dyn Fn(u8) -> bool),
+    (br"\D", &|x| !x.is_ascii_digit()),
+    (br"\w", &|x| x.is_ascii_alphanumeric() || x == b'_'),
+    (br"\W", &|x| !(x.is_ascii_alphanumeric() || x == b'_')),
+    (br"\s", &|x| b" \t\n\r".contains(&x)),
+    (br"\S", &|x| !b" \t\n\r".contains(&x)),
+    (br"[\w$]", &|x| x.is_ascii_alphanumeric() || x == b'_' || x == b'$'),
+    (br"[^\s,]", &|x| !b" \t\n\r,".contains(&x)),
+    (br"[\D]", &|x| !x.is_ascii_digit()),
+    (br"[\d\s]", &|x| x.is_ascii_digit() || b" \t\n\r".contains(&x)),
+    (br"[^\W]", &|x| x.is_ascii_alphanumeric() || x == b'_'),
+    (br"[\d-]", &|x| x.is_ascii_digit() || x == b'-'),
+    (br"[-\d]", &|x| x.is_ascii_digit() || x == b'-'),
+    (br"[a-c\d]", &|x| x.is_ascii_digit() || (b'a'..=b'c').contains(&x)),
+  ] {
+    let dfa = re2dfa([re]).unwrap();
+    for x in 0..=255 { assert_eq!(dfa.longest_match(&[x]).is_some(), f(x), "{} {:#x}", String::from_utf8_lossy(re), x); }
+  }
+  // non-ascii bytes are in the negations
+  assert!(accept(br"\W\S\D", b"\x80\xff\xe9"));
+  // a shorthand can't be an endpoint of a range
+  use ParseErrorKind::*;
+  for (re, offset) in [(&br"[a-\d]"[..], 3), (br"[\d-z]", 1), (br"[\w-\d]", 1), (br"[0-9a-\s]", 6), (br"x[^\S-a]", 3)] {
+    assert_eq!(err(re), (offset, InvalidRange), "{}", String::from_utf8_lossy(re));
+  }
+  assert_eq!(err(br"[\d-"), (0, UnclosedClass));
+}
+
+#[test]
+fn complement_intersection() {
+  let same = |a: &[u8], b: &[u8]| {
+    let (x, y) = (re2dfa([a]).unwrap(), re2dfa([b]).unwrap());
+    assert!(x.equivalent(&y).is_ok(), "{} {}", String::from_utf8_lossy(a), String::from_utf8_lossy(b));
+  };
+  // `~` applies to the following factor, and binds looser than suffixes
+  same(b"~ab", b"(~a)b");
+  same(b"~a*", b"~(a*)");
+  same(b"~~a", b"a");
+  // `&` binds looser than concatenation but tighter than `|`
+  same(b"ab&a.", b"(ab)&(a.)");
+  same(b"a|b&c|d", b"a|(b&c)|d");
+  same(b"a.&.b|c", b"((a.)&(.b))|c");
+  assert!(accept(b"~ab", b"bb") && accept(b"~ab", b"b") && !accept(b"~ab", b"ab") && !accept(b"~ab", b"a"));
+  assert!(accept(b"a|b&c|d", b"a") && !accept(b"a|b&c|d", b"b") && accept(b"a.&.b|c", b"ab") && !accept(b"a.&.b|c", b"aa"));
+  // escaped and in [], they are literal chars
+  for (re, input) in [(&br"\~a"[..], &b"~a"[..]), (br"a\&b", b"a&b"), (br"[~&]+", b"&~&"), (br"\&\&|\|\|", b"&&")] {
+    assert!(accept(re, input), "{}", String::from_utf8_lossy(re));
+  }
+  assert!(!accept(br"\~a", b"b"));
+  use ParseErrorKind::*;
+  for (re, offset, kind) in [
+    (&b"a&"[..], 1, EmptyOperand), (b"&a", 0, EmptyOperand), (b"a&&b", 1, EmptyOperand), (b"(a&)", 2, EmptyOperand),
+    (b"~", 0, EmptyOperand), (b"a~", 1, EmptyOperand), (b"(~)", 1, EmptyOperand), (b"~|a", 0, EmptyOperand), (b"a~~", 2, EmptyOperand),
+    (b"a&*", 2, DanglingQuantifier), (b"~*", 1, DanglingQuantifier),
+  ] {
+    assert_eq!(err(re), (offset, kind), "{}", String::from_utf8_lossy(re));
+  }
+}
@@ -0,0 +1,114 @@
+use re2dfa::*;
+
+fn dfa(re: &[&[u8]]) -> Dfa { re2dfa(re.iter().copied()).unwrap() }
+
+// the accept id of `dfa` on the whole `input`
+fn run(dfa: &Dfa, input: &[u8]) -> Option<u32> {
+  let mut state = 0;
+  for &ch in input { state = dfa.next(state, ch)?; }
+  dfa.acc(state)
+}
+
+// `c` really tells `l` and `r` apart
+fn check_counterexample(l: &Dfa, r: &Dfa, c: &Counterexample) {
+  assert_eq!((run(l, &c.input), run(r, &c.input)), (c.left, c.right));
+}
+
+#[test]
+fn counterexample() {
+  for (l, r, input) in [
+    (&[&b"a*"[..]][..], &[&b"a+"[..]][..], &b""[..]),
+    (&[br"(a|b)*abb"], &[br"(a|b)*ab"], b"ab"),
+    (&[b"if", br"[a-z]+"], &[b"if", br"[a-y]+"], b"z"),
+    (&[br"\d+"], &[br"\d{1,3}"], b"0000"),
+  ] {
+    let (l, r) = (dfa(l), dfa(r));
+    let c = l.equivalent(&r).err().unwrap();
+    assert_eq!(c.input, input);
+    check_counterexample(&l, &r, &c);
+    // the shortest counterexample is the same in the other direction, with the sides swapped
+    let c1 = r.equivalent(&l).err().unwrap();
+    assert_eq!((c1.input, c1.left, c1.right), (c.input, c.right, c.left));
+  }
+  let c = dfa(&[b"a*"]).equivalent(&dfa(&[b"a+"])).err().unwrap();
+  assert_eq!(c.to_string(), r#"on "", the left dfa accepts pattern 0, but the right dfa accepts nothing"#);
+}
+
+#[test]
+fn renaming() {
+  // the same patterns in another order
+  assert!(dfa(&[b"a", b"b"]).equivalent(&dfa(&[b"b", b"a"])).is_ok());
+  // an earlier pattern takes all strings of `a|c` except "c", so it is just `c`
+  assert!(dfa(&[b"a", b"b", b"c"]).equivalent(&dfa(&[b"a", b"b", b"a|c"])).is_ok());
+  assert!(dfa(&[b"if", br"[a-z]+", br"\d+"]).equivalent(&dfa(&[br"\d+", b"if", br"[a-z]+"])).is_ok());
+  // not minimized
+  let mut nfa = Nfa::from_re([&b"(a|b)*abb"[..], b"c+"]).unwrap();
+  nfa.compute_e_close();
+  assert!(Dfa::from_nfa(&nfa).equivalent(&dfa(&[b"c+", br"(a|b)*abb"])).is_ok());
+  // 2 ids can't be renamed to the same one, in either direction
+  for (l, r, input) in [
+    (&[&b"a"[..], b"b"][..], &[&b"a|b"[..]][..], &b"b"[..]),
+    (&[b"a|b"], &[b"a", b"b"], b"b"),
+    (&[b"a", b"b", b"c"], &[b"a", b"b|c"], b"c"),
+    (&[b"aa|b", b"a"], &[b"b", b"a", b"aa"], b"aa"),
+  ] {
+    let (l, r) = (dfa(l), dfa(r));
+    let c = l.equivalent(&r).err().unwrap();
+    assert_eq!(c.input, input);
+    check_counterexample(&l, &r, &c);
+    assert!(c.left.is_some() && c.right.is_some());
+  }
+}
+
+#[test]
+fn combine_acc() {
+  // the id of `self` if it accepts, otherwise the id of `other`, which is not renumbered
+  let (a, b) = (dfa(&[b"if", br"[a-z]+"]), dfa(&[br"\d+", br"[a-f]+"]));
+  let union = a.union(&b);
+  for (input, id) in [(&b"if"[..], Some(0)), (b"abc", Some(1)), (b"x", Some(1)), (b"12", Some(0)), (b"a1", None), (b"", None)] {
+    assert_eq!(run(&union, input), id, "{}", String::from_utf8_lossy(input));
+  }
+  assert!(union.equivalent(&dfa(&[b"if|[0-9]+", br"[a-z]+"])).is_ok());
+  let inter = a.intersection(&b);
+  for (input, id) in [(&b"fed"[..], Some(1)), (b"if", None), (b"x", None), (b"12", None)] {
+    assert_eq!(run(&inter, input), id, "{}", String::from_utf8_lossy(input));
+  }
+  assert!(inter.equivalent(&dfa(&[br"[a-f]+"])).is_ok());
+  // the ids of `other` in the result, with `self` accepting nothing there
+  let sym = b.symmetric_difference(&a);
+  for (input, id) in [(&b"12"[..], Some(0)), (b"if", Some(0)), (b"x", Some(1)), (b"fed", None)] {
+    assert_eq!(run(&sym, input), id, "{}", String::from_utf8_lossy(input));
+  }
+  let diff = a.difference(&b);
+  assert!(diff.equivalent(&dfa(&[b"if", br"[a-z]*[g-z][a-z]*"])).is_ok());
+  assert!(b.difference(&b).nodes.iter().all(|n| n.0.is_none()));
+}
+
+#[test]
+fn longest_match() {
+  // identifiers minus keywords, the keywords are only removed as whole strings
+  let ident = dfa(&[br"[a-z]+"]).difference(&dfa(&[b"if|else"]));
+  for (input, m) in [(&b"if"[..], Some((0, 1))), (b"iff", Some((0, 3))), (b"else", Some((0, 3))), (b"elsewhere x", Some((0, 9))), (b"1", None)] {
+    assert_eq!(ident.longest_match(input), m, "{}", String::from_utf8_lossy(input));
+  }
+  assert_eq!(ident.tokens(b"if x").map(|t| t.map(|(_, r)| r)).collect::<Vec<_>>(), [Ok(0..1), Ok(1..2), Err(2), Ok(3..4)]);
+  let sym = dfa(&[br"[a-c]+"]).symmetric_difference(&dfa(&[br"[b-d]+"]));
+  for (input, m) in [(&b"bc"[..], None), (b"bcd", Some((0, 3))), (b"abc", Some((0, 3))), (b"bca", Some((0, 3))), (b"bcad", Some((0, 3)))] {
+    assert_eq!(sym.longest_match(input), m, "{}", String::from_utf8_lossy(input));
+  }
+}
+
+#[test]
+fn is_subset() {
+  assert_eq!(dfa(&[b"a"]).is_subset(&dfa(&[b"b"])), Err(b"a".to_vec()));
+  assert_eq!(dfa(&[br"[a-z]+"]).is_subset(&dfa(&[br"\w+"])), Ok(()));
+  assert_eq!(dfa(&[b"if", b"else"]).is_subset(&dfa(&[br"[a-z]+"])), Ok(()));
+  assert_eq!(dfa(&[br"(ab)*"]).is_subset(&dfa(&[br"(ab)+"])), Err(b"".to_vec()));
+  assert_eq!(dfa(&[br"a{1,5}"]).is_subset(&dfa(&[br"a{1,3}|aaaa"])), Err(b"aaaaa".to_vec()));
+  // the witness is accepted by the left one only
+  let (l, r) = (dfa(&[br"\w+"]), dfa(&[br"[a-z]+"]));
+  let w = l.is_subset(&r).err().unwrap();
+  assert!(w.len() == 1 && run(&l, &w).is_some() && run(&r, &w).is_none());
+  // accept ids are ignored
+  assert_eq!(dfa(&[b"a", b"b"]).is_subset(&dfa(&[b"b", b"a"])), Ok(()));
+}
@@ -0,0 +1,71 @@
+#![cfg(feature = "serde")]
+use re2dfa::*;
+use serde_json::{json, Value};
+
+const PATTERNS: &[&[u8]] = &[b"if", br"[a-z]+", br"~(a*)&b.", br"\d{2,3}"];
+
+#[test]
+fn round_trip() {
+  let dfa = re2dfa(PATTERNS.iter().copied()).unwrap();
+  let s = serde_json::to_string(&dfa).unwrap();
+  let dfa1 = serde_json::from_str::<Dfa>(&s).unwrap();
+  assert!(dfa1.equivalent(&dfa).is_ok());
+  assert_eq!(serde_json::to_string(&dfa1).unwrap(), s);
+  for nfa in [Nfa::from_re(PATTERNS.iter().copied()).unwrap(), Nfa::thompson(&parse_all(PATTERNS.iter().copied()).unwrap())] {
+    let s = serde_json::to_string(&nfa).unwrap();
+    let nfa1 = serde_json::from_str::<Nfa>(&s).unwrap();
+    assert_eq!(nfa1.e_close, nfa.e_close);
+    assert_eq!(serde_json::to_string(&nfa1).unwrap(), s);
+    assert!(Dfa::from_nfa(&nfa1).equivalent(&Dfa::from_nfa(&nfa)).is_ok());
+  }
+  let re = parse_all([&b"a|~b&c*"[..], br"[^\d]?x{2}"]).unwrap();
+  assert_eq!(serde_json::from_str::<Vec<Re>>(&serde_json::to_string(&re).unwrap()).unwrap(), re);
+}
+
+fn err<T: serde::de::DeserializeOwned>(v: Value) -> String {
+  match serde_json::from_value::<T>(v) {
+    Ok(_) => panic!("invalid value accepted"),
+    Err(e) => e.to_string(),
+  }
+}
+
+#[test]
+fn invalid_re() {
+  for (v, msg) in [
+    (json!({"Disjunction": [{"Concat": []}, {"Ch": 97}]}), "Concat should have at least 1 child"),
+    (json!({"Kleene": {"Disjunction": []}}), "Disjunction should have at least 1 child"),
+    (json!({"Complement": {"Intersection": []}}), "Intersection should have at least 1 child"),
+  ] {
+    assert!(err::<Re>(v).contains(msg));
+  }
+  // a single child is fine
+  let re = serde_json::from_value::<Re>(json!({"Concat": [{"Kleene": {"Ch": 97}}]})).unwrap();
+  let expect = re2dfa([&b"a*"[..]]).unwrap();
+  for dfa in [Dfa::from_nfa(&Nfa::from_re1(std::slice::from_ref(&re))), Dfa::from_re(std::slice::from_ref(&re))] {
+    let mut dfa = dfa;
+    dfa.minimize();
+    assert!(dfa.equivalent(&expect).is_ok());
+  }
+}
+
+#[test]
+fn invalid_automata() {
+  let dfa = serde_json::to_value(re2dfa(PATTERNS.iter().copied()).unwrap()).unwrap();
+  let nfa = serde_json::to_value(Nfa::from_re(PATTERNS.iter().copied()).unwrap()).unwrap();
+  let with = |v: &Value, key: &str, x: Value| {
+    let mut v = v.clone();
+    v[key] = x;
+    v
+  };
+  assert!(err::<Dfa>(with(&dfa, "ec_num", json!(1))).contains("is not within [0, ec_num)"));
+  assert!(err::<Dfa>(with(&dfa, "ec_num", json!(0))).contains("ec_num 0"));
+  assert!(err::<Dfa>(with(&dfa, "ec", json!([0, 1]))).contains("ec has 2 elements"));
+  assert!(err::<Dfa>(with(&dfa, "nodes", json!([]))).contains("at least 1 node"));
+  assert!(err::<Dfa>(with(&dfa, "nodes", json!([[null, {"0": 1}]]))).contains("doesn't exist"));
+  assert!(err::<Dfa>(with(&dfa, "nodes", json!([[null, {"200": 0}]]))).contains("not within [0, ec_num)"));
+  assert!(err::<Nfa>(with(&nfa, "e_close", json!([1]))).contains("e_close has 1 elements"));
+  let n = nfa["nodes"].as_array().unwrap().len();
+  assert!(err::<Nfa>(with(&nfa, "e_close", json!(vec![!0u32; n * tools::bitset::bslen(n)]))).contains("nodes that don't exist"));
+  let node = json!({"id": null, "eps_edges": [n], "edges": {}});
+  assert!(err::<Nfa>(with(&nfa, "nodes", json!([node]))).contains("doesn't exist"));
+}
@@ -0,0 +1,95 @@
+use re2dfa::*;
+
+fn err(spec: &str) -> SpecError { TokenSet::from_spec(spec).err().unwrap() }
+
+// the same tokens as `ts`, with the patterns written out by hand
+fn check(ts: &TokenSet, expect: &[(&str, &[u8])]) {
+  let mut e = TokenSet::new();
+  for (name, re) in expect { e.add(name, re).unwrap(); }
+  assert_eq!(ts.tokens.iter().map(|t| &t.name).collect::<Vec<_>>(), e.tokens.iter().map(|t| &t.name).collect::<Vec<_>>());
+  assert_eq!(ts.patterns.iter().map(|p| p.0).collect::<Vec<_>>(), e.patterns.iter().map(|p| p.0).collect::<Vec<_>>());
+  assert!(ts.build().equivalent(&e.build()).is_ok());
+}
+
+#[test]
+fn reference() {
+  let ts = TokenSet::from_spec(r"
+DIGIT  [0-9]
+# a definition can use earlier ones
+INT    {DIGIT}+|0x[0-9a-f]+
+ID     [a-zA-Z_]\w*
+%%
+{INT}(\.{DIGIT}*)?  NUM
+if                  IF
+{ID}                IDENT
+{DIGIT}{2,3}x       X
+").unwrap();
+  check(&ts, &[("NUM", br"([0-9]+|0x[0-9a-f]+)(\.[0-9]*)?"), ("IF", b"if"), ("IDENT", br"[a-zA-Z_]\w*"), ("X", br"[0-9]{2,3}x")]);
+  // the definition is wrapped in (), so `|` doesn't escape from it
+  let ts = TokenSet::from_spec("AB a|b\n%%\nx{AB}y XY").unwrap();
+  check(&ts, &[("XY", b"x(a|b)y")]);
+  assert_eq!(err("A a\n%%\n{A}{B} X"), SpecError { line: 3, col: 4, kind: SpecErrorKind::UndefinedReference("B".to_owned()) });
+  // a definition can't use later ones
+  assert_eq!(err("A {B}\nB b\n%%\n{A} X").kind, SpecErrorKind::UndefinedReference("B".to_owned()));
+  assert_eq!(err("A a\nA b\n%%\n{A} X"), SpecError { line: 2, col: 1, kind: SpecErrorKind::DuplicateDefinition("A".to_owned()) });
+}
+
+#[test]
+fn no_expand() {
+  // `{` inside [] or after `\` is not a reference, and neither is a repetition
+  let ts = TokenSet::from_spec(r"
+D [0-9]
+%%
+[{D}]+     CLASS
+\{D\}      BRACE
+[^{D}]     NOT
+a{2}       REPEAT
+[\]{D}]    ESCAPED_BRACKET
+").unwrap();
+  check(&ts, &[("CLASS", b"[{D}]+"), ("BRACE", br"\{D\}"), ("NOT", b"[^{D}]"), ("REPEAT", b"aa"), ("ESCAPED_BRACKET", br"[\]{D}]")]);
+  let dfa = ts.build();
+  assert_eq!(dfa.longest_match(b"{D}x"), Some((0, 3)));
+  assert_eq!(dfa.longest_match(b"1"), Some((2, 1)));
+  // `\{D}` escapes `{`, and the remaining `}` is an error
+  assert_eq!(err("D [0-9]\n%%\n\\{D} X").to_string(), "3:4: unexpected character");
+}
+
+#[test]
+fn layout() {
+  // leading whitespace, comments and empty lines, rules with the same name, and no definitions section
+  let ts = TokenSet::from_spec("
+  # comment
+    if      IF
+\t[a-z]+  IDENT
+
+  #       another comment
+[#][a-z]+  DIRECTIVE
+  \\d+     INT
+  0x[0-9a-f]+ INT
+  a b c    SPACED
+").unwrap();
+  check(&ts, &[("IF", b"if"), ("IDENT", b"[a-z]+"), ("DIRECTIVE", b"#[a-z]+"), ("INT", br"\d+"), ("INT", b"0x[0-9a-f]+"), ("SPACED", b"a b c")]);
+  assert_eq!(ts.tokens.len(), 5);
+  assert_eq!(TokenSet::from_spec("# only comments\n\n  # x\n").unwrap().tokens.len(), 0);
+  // `%%` is not a rule
+  assert!(TokenSet::from_spec("%%\n  if IF").is_ok());
+}
+
+#[test]
+fn error_position() {
+  for (spec, msg) in [
+    ("  [ab X", "1:3: unclosed character class"),
+    ("if IF\n (a X", "2:2: unclosed group"),
+    ("D [0-9]\n%%\n  {D}** X", "3:7: dangling quantifier"),
+    // an error inside the expansion of a reference points to the reference
+    ("D (a\n%%\n{D} X", "1:3: unclosed group"),
+    ("P a|\n%%\nx{P} X", "1:4: empty alternative"),
+    ("\n\n    if", "3:5: expect a name and a pattern"),
+    ("A\n%%\na X", "1:1: expect a name and a pattern"),
+    ("if 1F", "1:4: `1F` is not a valid name"),
+    ("1D [0-9]\n%%\na X", "1:1: `1D` is not a valid name"),
+    ("a X\n\tb{C} Y", "2:3: `C` is not defined"),
+  ] {
+    assert_eq!(err(spec).to_string(), msg, "{:?}", spec);
+  }
+}
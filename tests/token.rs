@@ -0,0 +1,76 @@
+use re2dfa::*;
+
+fn tokens(input: &[u8], dfa: &Dfa, ts: &TokenSet) -> Vec<String> {
+  dfa.tokens(input).map(|t| {
+    let (id, r) = t.unwrap();
+    format!("{}:{}", ts.name(id), std::str::from_utf8(&input[r]).unwrap())
+  }).collect()
+}
+
+#[test]
+fn priority() {
+  // `IF` wins on "if" whether it is registered before or after `IDENT`, because of its priority
+  for if_first in [true, false] {
+    let mut ts = TokenSet::new();
+    if if_first { ts.add("IF", b"if").unwrap().priority(1); }
+    ts.add("IDENT", b"[a-z]+").unwrap();
+    if !if_first { ts.add("IF", b"if").unwrap().priority(1); }
+    ts.add("SPACE", b" +").unwrap();
+    let dfa = ts.build();
+    assert_eq!(tokens(b"if iff i", &dfa, &ts), ["IF:if", "SPACE: ", "IDENT:iff", "SPACE: ", "IDENT:i"]);
+  }
+  // with the same priority, the token registered first wins
+  let mut ts = TokenSet::new();
+  ts.add("IDENT", b"[a-z]+").unwrap();
+  ts.add("IF", b"if").unwrap();
+  assert_eq!(tokens(b"if", &ts.build(), &ts), ["IDENT:if"]);
+  // a negative priority loses to the default one
+  let mut ts = TokenSet::new();
+  ts.add("ANY", b".").unwrap().priority(-1);
+  ts.add("DIGIT", b"[0-9]").unwrap();
+  assert_eq!(tokens(b"1a", &ts.build(), &ts), ["DIGIT:1", "ANY:a"]);
+}
+
+#[test]
+fn patterns() {
+  // several patterns of one token share its id, its skip flag and its priority
+  let mut ts = TokenSet::new();
+  ts.add("INT", br"[0-9]+").unwrap();
+  ts.add("IDENT", b"[a-z]+").unwrap();
+  ts.add("INT", br"0x[0-9a-f]+").unwrap().priority(1);
+  ts.add("SPACE", b" ").unwrap().skip(true);
+  ts.add("SPACE", b"#[a-z]*").unwrap();
+  assert_eq!(ts.tokens.len(), 3);
+  assert_eq!(ts.patterns.iter().map(|p| p.0).collect::<Vec<_>>(), [0, 1, 0, 2, 2]);
+  assert_eq!((ts.id("INT"), ts.id("SPACE"), ts.id("ELSE")), (Some(0), Some(2), None));
+  assert!(ts.tokens.iter().map(|t| (t.skip, t.priority)).eq([(false, 1), (false, 0), (true, 0)]));
+  let dfa = ts.build();
+  assert_eq!(tokens(b"12 0xff#x ab", &dfa, &ts), ["INT:12", "SPACE: ", "INT:0xff", "SPACE:#x", "SPACE: ", "IDENT:ab"]);
+  // the accept ids are token ids, so the dfa is as small as that of the merged patterns
+  let merged = re2dfa([&br"[0-9]+|0x[0-9a-f]+"[..], b"[a-z]+", b" |#[a-z]*"]).unwrap();
+  assert!(dfa.equivalent(&merged).is_ok());
+  assert_eq!(dfa.nodes.len(), merged.nodes.len());
+  // the index in the error is the index of the pattern
+  assert_eq!(ts.add("X", b"(").err().map(|e| e.idx), Some(5));
+  assert_eq!(ts.tokens.len(), 3);
+}
+
+#[test]
+fn names() {
+  let mut ts = TokenSet::new();
+  ts.add("IF", b"if").unwrap();
+  ts.add("IDENT", b"[a-z]+").unwrap();
+  ts.add("SPACE", b" +").unwrap().skip(true);
+  let dfa = ts.build();
+  let dot = dfa.print_dot_with(&ts).to_string();
+  assert!(dot.contains(r"\nacc:IF") && dot.contains(r"\nacc:IDENT") && dot.contains(r"\nacc:SPACE"));
+  assert!(!dot.contains(r"\nacc:0") && dfa.print_dot().to_string().contains(r"\nacc:0"));
+  let code = dfa.gen_rust_with(&ts).to_string();
+  assert!(code.contains("pub const TOKEN_NUM: usize = 3;\npub const IF: u32 = 0;\npub const IDENT: u32 = 1;\npub const SPACE: u32 = 2;\n"));
+  assert!(code.contains(r#"pub static NAMES: [&str; TOKEN_NUM] = ["IF", "IDENT", "SPACE"];"#));
+  assert!(code.contains("pub static SKIP: [bool; TOKEN_NUM] = [false, false, true];"));
+  // the rest of the module is the same as `gen_rust()`
+  let plain = dfa.gen_rust().to_string();
+  assert!(code.ends_with(&plain[plain.find("pub static EC:").unwrap()..]));
+  assert!(!plain.contains("TOKEN_NUM"));
+}